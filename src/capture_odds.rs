@@ -6,6 +6,47 @@ pub struct CaptureOdds {
     capture_odds: Vec<f64>,
     attacker_casualties: Vec<f64>,
     defender_casualties: Vec<f64>,
+    // second moments of the casualties, for the variances
+    attacker_casualties_sq: Vec<f64>,
+    defender_casualties_sq: Vec<f64>,
+}
+
+/// How likely each possible end of a fight is, from one starting state,
+/// under the same model `CaptureOdds` uses.
+#[derive(Clone,Debug)]
+pub struct TerminalDistribution {
+    /// `captured[n]` is the probability that the defenders are wiped out
+    /// with `n` attackers left.
+    pub captured: Vec<f64>,
+    /// `repelled[n]` is the probability that the attackers are whittled
+    /// down to one (who can't attack) with `n` defenders left.
+    pub repelled: Vec<f64>,
+}
+
+impl TerminalDistribution {
+    pub fn capture_odds(&self) -> f64 {
+        self.captured.iter().sum()
+    }
+    /// `ret[n]` is the probability that the attackers lose `n` crew.
+    pub fn attacker_casualties(&self) -> Vec<f64> {
+        let attackers = self.captured.len() - 1;
+        let mut ret = vec![0.0; attackers];
+        for (n, &p) in self.captured.iter().enumerate().skip(1) {
+            ret[attackers - n] += p;
+        }
+        ret[attackers - 1] += self.repelled.iter().sum::<f64>();
+        ret
+    }
+    /// `ret[n]` is the probability that the defenders lose `n` crew.
+    pub fn defender_casualties(&self) -> Vec<f64> {
+        let defenders = self.repelled.len() - 1;
+        let mut ret = vec![0.0; defenders + 1];
+        for (n, &p) in self.repelled.iter().enumerate() {
+            ret[defenders - n] += p;
+        }
+        ret[defenders] += self.capture_odds();
+        ret
+    }
 }
 
 impl CaptureOdds {
@@ -21,11 +62,15 @@ impl CaptureOdds {
         let mut capture_odds = Vec::with_capacity(vec_size);
         let mut attacker_casualties = Vec::with_capacity(vec_size);
         let mut defender_casualties = Vec::with_capacity(vec_size);
+        let mut attacker_casualties_sq = Vec::with_capacity(vec_size);
+        let mut defender_casualties_sq = Vec::with_capacity(vec_size);
         // row 1 = single attacker, cannot attack
         for _ in 0..defender_strengths.len() {
             capture_odds.push(0.0);
             attacker_casualties.push(0.0);
             defender_casualties.push(0.0);
+            attacker_casualties_sq.push(0.0);
+            defender_casualties_sq.push(0.0);
         }
         let mut up = 0; // the index of this column on the preview row
         for attacker_count in 2 .. attacker_strengths.len()+1 {
@@ -33,12 +78,19 @@ impl CaptureOdds {
             // column 1 = single defender
             let odds = attack_strength / (attack_strength + defender_strengths[0]);
             capture_odds.push(odds + (1.0 - odds) * capture_odds[up]);
+            attacker_casualties_sq.push((1.0 - odds) * (attacker_casualties_sq[up] + 2.0 * attacker_casualties[up] + 1.0));
+            defender_casualties_sq.push(odds + (1.0 - odds) * defender_casualties_sq[up]);
             attacker_casualties.push((1.0 - odds) * (attacker_casualties[up] + 1.0));
             defender_casualties.push(odds + (1.0 - odds) * (defender_casualties[up]));
             up += 1;
             for defender_count in 2 .. defender_strengths.len()+1 {
                 let odds = attack_strength / (attack_strength + defender_strengths[defender_count-1]);
                 capture_odds.push(odds * capture_odds.last().unwrap() + (1.0 - odds) * capture_odds[up]);
+                // (X+1)^2 = X^2 + 2X + 1, so each second moment needs the
+                // neighbouring means, which haven't been pushed yet
+                let left = attacker_casualties.len() - 1;
+                attacker_casualties_sq.push(odds * attacker_casualties_sq[left] + (1.0 - odds) * (attacker_casualties_sq[up] + 2.0 * attacker_casualties[up] + 1.0));
+                defender_casualties_sq.push(odds * (defender_casualties_sq[left] + 2.0 * defender_casualties[left] + 1.0) + (1.0 - odds) * defender_casualties_sq[up]);
                 attacker_casualties.push(odds * attacker_casualties.last().unwrap() + (1.0 - odds) * (attacker_casualties[up] + 1.0));
                 defender_casualties.push(odds * (defender_casualties.last().unwrap() + 1.0) + (1.0 - odds) * defender_casualties[up]);
                 up += 1;
@@ -51,6 +103,8 @@ impl CaptureOdds {
             capture_odds,
             attacker_casualties,
             defender_casualties,
+            attacker_casualties_sq,
+            defender_casualties_sq,
         }
     }
     pub fn crew_counts_to_index(&self,
@@ -85,6 +139,58 @@ impl CaptureOdds {
                                               remaining_defenders);
         self.defender_casualties[index]
     }
+    pub fn attacker_casualty_variance(&self, remaining_attackers: u32,
+                                      remaining_defenders: u32)
+                                      -> f64 {
+        let index = self.crew_counts_to_index(remaining_attackers,
+                                              remaining_defenders);
+        let mean = self.attacker_casualties[index];
+        (self.attacker_casualties_sq[index] - mean * mean).max(0.0)
+    }
+    pub fn defender_casualty_variance(&self, remaining_attackers: u32,
+                                      remaining_defenders: u32)
+                                      -> f64 {
+        let index = self.crew_counts_to_index(remaining_attackers,
+                                              remaining_defenders);
+        let mean = self.defender_casualties[index];
+        (self.defender_casualties_sq[index] - mean * mean).max(0.0)
+    }
+    /// Works out how likely each possible end of the fight is, pushing the
+    /// probability down one row of the table at a time.
+    pub fn terminal_distribution(&self, remaining_attackers: u32,
+                                 remaining_defenders: u32)
+                                 -> TerminalDistribution {
+        let attackers = remaining_attackers as usize;
+        let defenders = remaining_defenders as usize;
+        let mut ret = TerminalDistribution {
+            captured: vec![0.0; attackers + 1],
+            repelled: vec![0.0; defenders + 1],
+        };
+        if attackers <= 1 {
+            ret.repelled[defenders] = 1.0;
+            return ret
+        }
+        // row[d] = probability of passing through (a, d)
+        let mut row = vec![0.0; defenders + 1];
+        let mut next_row = vec![0.0; defenders + 1];
+        row[defenders] = 1.0;
+        for a in (2 ..= attackers).rev() {
+            let attack_strength = self.attacker_strengths[a - 1];
+            for d in (1 ..= defenders).rev() {
+                let p = row[d];
+                if p == 0.0 { continue }
+                let odds = attack_strength / (attack_strength + self.defender_strengths[d - 1]);
+                if d == 1 { ret.captured[a] += p * odds }
+                else { row[d - 1] += p * odds }
+                next_row[d] += p * (1.0 - odds);
+            }
+            std::mem::swap(&mut row, &mut next_row);
+            for x in next_row.iter_mut() { *x = 0.0 }
+        }
+        // whatever made it down to row 1 was repelled
+        ret.repelled.copy_from_slice(&row);
+        ret
+    }
     pub fn attacker_power(&self, remaining_attackers: u32) -> f64 {
         self.attacker_strengths[remaining_attackers as usize - 1]
     }
@@ -92,3 +198,259 @@ impl CaptureOdds {
         self.defender_strengths[remaining_defenders as usize- 1]
     }
 }
+
+// The tables are checked against an independent oracle rather than numbers
+// pasted in from somewhere. The game's own code isn't available to dump
+// tables from, so the oracle works each entry out the long way: each crew
+// member in turn picks the best weapon still left, and probability is pushed
+// forward through the fight from the starting state and added up by how it
+// ends, instead of using calculate_strengths and the backward recurrence
+// above. The oracle is itself checked against values worked out by hand. If
+// the tables ever disagree with ones dumped from the game, the game wins.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Government, Weapon};
+    use crate::simulation::calculate_strengths;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    const HUMAN: (f64, f64) = (1.0, 2.0);
+    const KORATH: (f64, f64) = (1.2, 2.6);
+    const NERVE_GAS: (f64, f64) = (2.8, 0.8);
+    const LASER_RIFLE: (f64, f64) = (0.6, 0.8);
+    const SECURITY_STATION: (f64, f64) = (0.0, 3.4);
+    const REPEATER_RIFLE: (f64, f64) = (1.6, 2.4);
+
+    type Weapons<'a> = &'a [((f64, f64), u32)];
+
+    /// The oracle's strengths: `(attack, defense)` running totals for 1 to
+    /// `crew` crew. For attack, each crew member in turn picks whichever
+    /// weapon left has the most attack, or fights bare-handed if there are
+    /// none; then they all put the weapons back and do the same for defense.
+    fn oracle_strengths(crew: u32, gov: (f64, f64), weps: Weapons) -> (Vec<f64>, Vec<f64>) {
+        let running = |strength: fn((f64, f64)) -> f64, gov: f64| {
+            let mut left: Vec<u32> = weps.iter().map(|x| x.1).collect();
+            let mut total = 0.0;
+            let mut ret = Vec::new();
+            for _ in 0 .. crew {
+                let mut best: Option<usize> = None;
+                for n in 0 .. weps.len() {
+                    if left[n] == 0 { continue }
+                    if best.map(|b| strength(weps[n].0) > strength(weps[b].0)).unwrap_or(true) {
+                        best = Some(n);
+                    }
+                }
+                total += gov;
+                if let Some(n) = best {
+                    left[n] -= 1;
+                    total += strength(weps[n].0);
+                }
+                ret.push(total);
+            }
+            ret
+        };
+        (running(|x| x.0, gov.0), running(|x| x.1, gov.1))
+    }
+
+    /// The oracle's capture odds and expected attacker and defender
+    /// casualties, starting from `a0` attackers and `d0` defenders.
+    fn oracle(attack: &[f64], defense: &[f64], a0: usize, d0: usize) -> (f64, f64, f64) {
+        // prob[a][d] is the probability of passing through (a, d); every
+        // round loses someone, so going down the diagonals of a + d visits
+        // each state after everything that leads to it
+        let mut prob = vec![vec![0.0; d0 + 1]; a0 + 1];
+        prob[a0][d0] = 1.0;
+        let (mut capture, mut attackers_lost, mut defenders_lost) = (0.0, 0.0, 0.0);
+        for total in (1 ..= a0 + d0).rev() {
+            for a in 1 ..= a0 {
+                if a > total || total - a > d0 { continue }
+                let d = total - a;
+                let p = prob[a][d];
+                if p == 0.0 { continue }
+                if d == 0 {
+                    capture += p;
+                    attackers_lost += p * (a0 - a) as f64;
+                    defenders_lost += p * d0 as f64;
+                }
+                else if a == 1 {
+                    // a lone attacker can't attack, so the fight is over
+                    attackers_lost += p * (a0 - 1) as f64;
+                    defenders_lost += p * (d0 - d) as f64;
+                }
+                else {
+                    let odds = attack[a - 1] / (attack[a - 1] + defense[d - 1]);
+                    prob[a][d - 1] += p * odds;
+                    prob[a - 1][d] += p * (1.0 - odds);
+                }
+            }
+        }
+        (capture, attackers_lost, defenders_lost)
+    }
+
+    /// Checks every entry of the three tables for a fight between these two
+    /// sides against the oracle.
+    fn check(attackers: u32, agov: (f64, f64), aweps: Weapons,
+             defenders: u32, dgov: (f64, f64), dweps: Weapons) {
+        let side = |crew, gov: (f64, f64), weps: Weapons| {
+            let weps: Vec<(Weapon, u32)> = weps.iter()
+                .map(|&((a, d), n)| (Weapon::new(a, d), n))
+                .collect();
+            calculate_strengths(crew, Government::new(gov.0, gov.1), &weps)
+        };
+        let odds = CaptureOdds::new(&side(attackers, agov, aweps), &side(defenders, dgov, dweps));
+        let (attack, _) = oracle_strengths(attackers, agov, aweps);
+        let (_, defense) = oracle_strengths(defenders, dgov, dweps);
+        for a in 1 ..= attackers {
+            for d in 1 ..= defenders {
+                let expected = oracle(&attack, &defense, a as usize, d as usize);
+                let actual = (odds.capture_odds(a, d), odds.attacker_casualties(a, d),
+                              odds.defender_casualties(a, d));
+                for &(name, actual, expected) in [
+                    ("capture_odds", actual.0, expected.0),
+                    ("attacker_casualties", actual.1, expected.1),
+                    ("defender_casualties", actual.2, expected.2),
+                ].iter() {
+                    assert!((actual - expected).abs() <= TOLERANCE,
+                            "{} at {} vs {}: got {}, expected {}", name, a, d, actual, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bare_crews() {
+        check(3, HUMAN, &[], 3, HUMAN, &[]);
+    }
+
+    #[test]
+    fn more_attackers_than_defenders() {
+        check(5, HUMAN, &[], 2, KORATH, &[]);
+    }
+
+    #[test]
+    fn outnumbered_attackers() {
+        check(2, HUMAN, &[], 5, HUMAN, &[]);
+    }
+
+    #[test]
+    fn fewer_weapons_than_crew() {
+        check(4, HUMAN, &[(NERVE_GAS, 2), (LASER_RIFLE, 1)],
+              3, HUMAN, &[(SECURITY_STATION, 1)]);
+    }
+
+    #[test]
+    fn weapons_outnumber_crew() {
+        // only the best weapon for each crew member counts, separately for
+        // attack and defense
+        check(3, HUMAN, &[(NERVE_GAS, 5), (LASER_RIFLE, 2)],
+              4, KORATH, &[(REPEATER_RIFLE, 6), (SECURITY_STATION, 3)]);
+    }
+
+    #[test]
+    fn oracle_strengths_by_hand() {
+        // three humans with 5 Nerve Gas and 2 Laser Rifles all attack with
+        // Nerve Gas (1 + 2.8 each), and defend with 2 + 0.8 whichever they
+        // hold. Four Korath with 6 Repeater Rifles and 3 Security Stations
+        // attack with rifles (1.2 + 1.6 each), and defend with the three
+        // stations (2.6 + 3.4) and then a rifle (2.6 + 2.4).
+        let close = |actual: &[f64], expected: &[f64]| {
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).abs() <= TOLERANCE, "got {:?}, expected {:?}", actual, expected);
+            }
+        };
+        let (attack, defense) = oracle_strengths(3, HUMAN, &[(NERVE_GAS, 5), (LASER_RIFLE, 2)]);
+        close(&attack, &[3.8, 7.6, 11.4]);
+        close(&defense, &[2.8, 5.6, 8.4]);
+        let (attack, defense) = oracle_strengths(4, KORATH, &[(REPEATER_RIFLE, 6), (SECURITY_STATION, 3)]);
+        close(&attack, &[2.8, 5.6, 8.4, 11.2]);
+        close(&defense, &[6.0, 12.0, 18.0, 23.0]);
+        // with fewer weapons than crew, the rest fight bare-handed
+        let (attack, defense) = oracle_strengths(4, HUMAN, &[(NERVE_GAS, 2), (LASER_RIFLE, 1)]);
+        close(&attack, &[3.8, 7.6, 9.2, 10.2]);
+        close(&defense, &[2.8, 5.6, 8.4, 10.4]);
+    }
+
+    #[test]
+    fn oracle_matches_hand_worked_values() {
+        // so the oracle isn't only checked against the code it checks. Two
+        // humans have 2 attack against one human's 2 defense, so they win
+        // half the time, and otherwise lose one and have to stop. Three have
+        // 3 attack, so they win 3/5 of the time at once, and otherwise lose
+        // one and are back to the even fight: 3/5 + 2/5 * 1/2 = 4/5, losing
+        // 2/5 * 1/2 * 1 + 2/5 * 1/2 * 2 = 3/5 crew on average.
+        let (_, defense) = oracle_strengths(1, HUMAN, &[]);
+        let (attack, _) = oracle_strengths(3, HUMAN, &[]);
+        for &(attackers, expected) in [(2, (0.5, 0.5, 0.5)), (3, (0.8, 0.6, 0.8))].iter() {
+            let actual = oracle(&attack, &defense, attackers, 1);
+            assert!((actual.0 - expected.0).abs() <= TOLERANCE);
+            assert!((actual.1 - expected.1).abs() <= TOLERANCE);
+            assert!((actual.2 - expected.2).abs() <= TOLERANCE);
+        }
+        // two humans, one with Nerve Gas, have 3.8 + 1 = 4.8 attack against
+        // a human with a Security Station's 2 + 3.4 = 5.4 defense, so they
+        // capture 4.8 / 10.2 = 8/17 of the time
+        let (attack, _) = oracle_strengths(2, HUMAN, &[(NERVE_GAS, 1)]);
+        let (_, defense) = oracle_strengths(1, HUMAN, &[(SECURITY_STATION, 1)]);
+        let actual = oracle(&attack, &defense, 2, 1);
+        assert!((actual.0 - 8.0 / 17.0).abs() <= TOLERANCE);
+        assert!((actual.1 - 9.0 / 17.0).abs() <= TOLERANCE);
+    }
+
+    /// A fight where you always attack and they always defend, which is the
+    /// fight the tables describe.
+    fn slugfest() -> crate::Boarding {
+        let you = crate::Side::new(9, Government::new(HUMAN.0, HUMAN.1),
+                                   vec![("Nerve Gas".to_owned(), Weapon::new(NERVE_GAS.0, NERVE_GAS.1), 3)]);
+        let them = crate::Side::new(7, Government::new(KORATH.0, KORATH.1),
+                                    vec![("Security Station".to_owned(), Weapon::new(SECURITY_STATION.0, SECURITY_STATION.1), 2)]);
+        let mut scenario = crate::Scenario::new(you, them);
+        scenario.enemy_rule = crate::EnemyRule::AlwaysDefend;
+        scenario.prepare().unwrap()
+    }
+
+    #[test]
+    fn terminal_distribution_adds_up() {
+        let boarding = slugfest();
+        let odds = &boarding.player_attack_odds;
+        for a in 1 ..= boarding.ucrew {
+            for d in 1 ..= boarding.mcrew {
+                let dist = odds.terminal_distribution(a, d);
+                let total: f64 = dist.captured.iter().chain(dist.repelled.iter()).sum();
+                assert!((total - 1.0).abs() <= TOLERANCE, "{} vs {}: adds up to {}", a, d, total);
+                assert!((dist.capture_odds() - odds.capture_odds(a, d)).abs() <= TOLERANCE);
+            }
+        }
+        let exact = boarding.exact_solution(&crate::AlwaysAttack);
+        let dist = odds.terminal_distribution(boarding.ucrew, boarding.mcrew);
+        assert!((dist.capture_odds() - exact.victory_odds(boarding.ucrew, boarding.mcrew)).abs() <= TOLERANCE);
+    }
+
+    #[test]
+    fn casualty_variance_matches_simulation() {
+        const TRIALS: usize = 200_000;
+        let boarding = slugfest();
+        let mut rng = crate::work_unit_rng(1, 0);
+        let mut lost = (Vec::with_capacity(TRIALS), Vec::with_capacity(TRIALS));
+        for _ in 0 .. TRIALS {
+            let ending = crate::sub_attempt(&mut rng, &boarding, &crate::AlwaysAttack);
+            lost.0.push((boarding.ucrew - ending.ucrew) as f64);
+            lost.1.push((boarding.mcrew - ending.mcrew) as f64);
+        }
+        let odds = &boarding.player_attack_odds;
+        for &(who, samples, exact) in [
+            ("attacker", &lost.0, odds.attacker_casualty_variance(boarding.ucrew, boarding.mcrew)),
+            ("defender", &lost.1, odds.defender_casualty_variance(boarding.ucrew, boarding.mcrew)),
+        ].iter() {
+            let n = TRIALS as f64;
+            let mean = samples.iter().sum::<f64>() / n;
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            let fourth = samples.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / n;
+            // four standard errors of the sample variance
+            let tolerance = 4.0 * ((fourth - variance * variance) / n).sqrt();
+            assert!((variance - exact).abs() < tolerance, "{}: simulated {}, exact {}",
+                    who, variance, exact);
+        }
+    }
+}
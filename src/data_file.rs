@@ -0,0 +1,155 @@
+use std::path::Path;
+
+/// One line of an Endless Sky data file, along with every more-indented line
+/// that follows it.
+#[derive(Clone,Debug)]
+pub struct DataNode {
+    pub tokens: Vec<String>,
+    pub children: Vec<DataNode>,
+}
+
+impl DataNode {
+    pub fn size(&self) -> usize { self.tokens.len() }
+    /// Returns the given token, or an empty string if there isn't one.
+    pub fn token(&self, index: usize) -> &str {
+        self.tokens.get(index).map(|x| x.as_str()).unwrap_or("")
+    }
+    /// Returns the given token as a number, if it is one.
+    pub fn value(&self, index: usize) -> Option<f64> {
+        self.tokens.get(index).and_then(|x| x.parse().ok())
+    }
+}
+
+// see DataFile::LoadData in DataFile.cpp
+fn tokenize(line: &str) -> (usize, Vec<String>) {
+    let mut chars = line.chars().peekable();
+    let mut indent = 0;
+    while let Some(&c) = chars.peek() {
+        if !c.is_whitespace() { break }
+        indent += 1;
+        chars.next();
+    }
+    let mut tokens = Vec::new();
+    loop {
+        while let Some(&c) = chars.peek() {
+            if !c.is_whitespace() { break }
+            chars.next();
+        }
+        let c = match chars.next() {
+            None | Some('#') => break,
+            Some(x) => x,
+        };
+        let mut token = String::new();
+        if c == '"' || c == '`' {
+            for next in &mut chars {
+                if next == c { break }
+                token.push(next);
+            }
+        }
+        else {
+            token.push(c);
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() { break }
+                token.push(next);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    (indent, tokens)
+}
+
+fn build(lines: &[(usize, Vec<String>)], pos: &mut usize,
+         parent_indent: Option<usize>) -> Vec<DataNode> {
+    let mut ret = Vec::new();
+    while *pos < lines.len() {
+        let indent = lines[*pos].0;
+        if let Some(parent_indent) = parent_indent {
+            if indent <= parent_indent { break }
+        }
+        let tokens = lines[*pos].1.clone();
+        *pos += 1;
+        let children = build(lines, pos, Some(indent));
+        ret.push(DataNode { tokens, children });
+    }
+    ret
+}
+
+/// Parses the text of a data file into its top-level nodes.
+pub fn parse(text: &str) -> Vec<DataNode> {
+    let lines: Vec<(usize, Vec<String>)> = text.lines().map(tokenize)
+        .filter(|x| !x.1.is_empty()).collect();
+    let mut pos = 0;
+    build(&lines, &mut pos, None)
+}
+
+/// Loads every data file at the given path. A directory is searched
+/// recursively for `.txt` files, which are loaded in name order, the same way
+/// the game does it.
+pub fn load_path(path: &Path, out: &mut Vec<DataNode>) -> std::io::Result<()> {
+    if path.is_dir() {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            entries.push(entry?.path());
+        }
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir()
+            || entry.extension().map(|x| x == "txt").unwrap_or(false) {
+                load_path(&entry, out)?;
+            }
+        }
+    }
+    else {
+        out.append(&mut parse(&std::fs::read_to_string(path)?));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_and_backticks() {
+        let nodes = parse("outfit \"Nerve Gas\"\n\t`capture \"attack\"` 2.8\n");
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].tokens, vec!["outfit", "Nerve Gas"]);
+        let attribute = &nodes[0].children[0];
+        assert_eq!(attribute.tokens, vec!["capture \"attack\"", "2.8"]);
+        assert_eq!(attribute.value(1), Some(2.8));
+    }
+
+    #[test]
+    fn comments() {
+        let nodes = parse("# a whole line\noutfit Thing # the rest\n\t\"#hash\" 1\n\t# indented\n");
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].tokens, vec!["outfit", "Thing"]);
+        assert_eq!(nodes[0].children.len(), 1);
+        assert_eq!(nodes[0].children[0].tokens, vec!["#hash", "1"]);
+    }
+
+    #[test]
+    fn mixed_indentation() {
+        // every whitespace character counts as one level, whether it's a tab
+        // or a space
+        let nodes = parse("ship Hull\n\tattributes\n\t\tbunks 3\n  \"required crew\" 1\n \toutfits\n\t \t\"Laser Rifle\" 2\nship Other\n");
+        assert_eq!(nodes.len(), 2);
+        let hull = &nodes[0];
+        assert_eq!(hull.children.len(), 1);
+        let attributes = &hull.children[0];
+        assert_eq!(attributes.tokens, vec!["attributes"]);
+        let grandchildren: Vec<&str> = attributes.children.iter().map(|x| x.token(0)).collect();
+        assert_eq!(grandchildren, vec!["bunks", "required crew", "outfits"]);
+        assert_eq!(attributes.children[2].children[0].tokens, vec!["Laser Rifle", "2"]);
+        assert_eq!(nodes[1].tokens, vec!["ship", "Other"]);
+    }
+
+    #[test]
+    fn blank_lines_and_unclosed_quotes() {
+        let nodes = parse("outfit A\n\n\t\t\n\tmass 1\noutfit \"B\n");
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].children[0].tokens, vec!["mass", "1"]);
+        assert_eq!(nodes[1].tokens, vec!["outfit", "B"]);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::simulation::{round_powers, round_is_fought};
+use crate::capture_odds::CaptureOdds;
+use crate::state_table::StateTable;
+use crate::strategy::*;
+
+/// The odds when the enemy boards your ship, from every state up to a given
+/// pair of crew counts.
+///
+/// The enemy decides whether to attack by its rule, as usual, but it needs
+/// two crew to attack, the same as you. If neither side attacks, it gives up
+/// and leaves. You lose your ship if you run out of crew, and if you fight
+/// back hard enough, you can capture theirs instead. Decisions are made one
+/// round at a time.
+pub struct DefenseSolution {
+    values: StateTable<StateValue>,
+}
+
+/// What one state works out to, if you take a given action there.
+#[derive(Clone,Copy)]
+struct StateValue {
+    loss_odds: f64,
+    capture_odds: f64,
+    casualties: f64,
+}
+
+impl DefenseSolution {
+    /// Follows the given strategy. Retreating isn't an option on your own
+    /// ship, so a strategy that wants to retreat defends instead.
+    pub fn with_strategy(ucrew: u32, mcrew: u32,
+                         player_attack_odds: &CaptureOdds,
+                         player_defense_odds: &CaptureOdds,
+                         enemy_rule: EnemyRule,
+                         strategy: &dyn Strategy)
+                         -> DefenseSolution {
+        DefenseSolution::solve(ucrew, mcrew, player_attack_odds, player_defense_odds,
+                               enemy_rule, |situation, _, _| {
+            strategy.decide(situation).action == Action::Attack
+        })
+    }
+    /// Does whatever gives the best odds of keeping your ship, and then
+    /// whatever costs the fewest crew.
+    pub fn optimal(ucrew: u32, mcrew: u32,
+                   player_attack_odds: &CaptureOdds,
+                   player_defense_odds: &CaptureOdds,
+                   enemy_rule: EnemyRule)
+                   -> DefenseSolution {
+        DefenseSolution::solve(ucrew, mcrew, player_attack_odds, player_defense_odds,
+                               enemy_rule, |_, attack, defend| {
+            if attack.loss_odds != defend.loss_odds { attack.loss_odds < defend.loss_odds }
+            else { attack.casualties < defend.casualties }
+        })
+    }
+    /// `choose` returns whether to attack, given the situation and what
+    /// attacking and defending would each lead to.
+    fn solve<F>(ucrew: u32, mcrew: u32,
+                player_attack_odds: &CaptureOdds,
+                player_defense_odds: &CaptureOdds,
+                enemy_rule: EnemyRule, choose: F)
+                -> DefenseSolution
+    where F: Fn(&Situation, StateValue, StateValue) -> bool {
+        let mut ret = DefenseSolution { values: StateTable::new(ucrew, mcrew) };
+        for u in 0 ..= ucrew {
+            for m in 0 ..= mcrew {
+                let value = if u == 0 {
+                    StateValue { loss_odds: 1.0, capture_odds: 0.0, casualties: 0.0 }
+                }
+                else if m == 0 {
+                    StateValue { loss_odds: 0.0, capture_odds: 1.0, casualties: 0.0 }
+                }
+                else {
+                    let situation = Situation { ucrew: u, mcrew: m, player_attack_odds, player_defense_odds, enemy_rule };
+                    let enemy_attacks = m >= 2 && situation.enemy_attacks();
+                    let defend = ret.action_value(&situation, false, enemy_attacks);
+                    if u < 2 { defend }
+                    else {
+                        let attack = ret.action_value(&situation, true, enemy_attacks);
+                        if choose(&situation, attack, defend) { attack } else { defend }
+                    }
+                };
+                ret.values.push(value);
+            }
+        }
+        ret
+    }
+    /// What happens if you take the given action for one round and follow
+    /// the solution afterward. Only valid once every smaller state has been
+    /// solved.
+    fn action_value(&self, situation: &Situation, you_attack: bool,
+                    enemy_attacks: bool) -> StateValue {
+        // if nobody attacks, they go home
+        let action = if you_attack { Action::Attack } else { Action::Defend };
+        if !round_is_fought(action, enemy_attacks) {
+            return StateValue { loss_odds: 0.0, capture_odds: 0.0, casualties: 0.0 }
+        }
+        let (u, m) = (situation.ucrew, situation.mcrew);
+        let (upow, tpow) = round_powers(u, m, you_attack, enemy_attacks,
+                                        situation.player_attack_odds,
+                                        situation.player_defense_odds);
+        let win = upow / tpow;
+        let (won, lost) = (self.values[(u, m - 1)], self.values[(u - 1, m)]);
+        StateValue {
+            loss_odds: win * won.loss_odds + (1.0 - win) * lost.loss_odds,
+            capture_odds: win * won.capture_odds + (1.0 - win) * lost.capture_odds,
+            casualties: win * won.casualties + (1.0 - win) * (lost.casualties + 1.0),
+        }
+    }
+    /// The odds that they capture your ship.
+    pub fn loss_odds(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.values[(ucrew, mcrew)].loss_odds
+    }
+    /// The odds that you capture their ship instead.
+    pub fn capture_odds(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.values[(ucrew, mcrew)].capture_odds
+    }
+    /// The crew you can expect to lose.
+    pub fn casualties(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.values[(ucrew, mcrew)].casualties
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use crate::*;
+
+    /// Plays out one boarding of your ship, a round at a time, and returns
+    /// whether you lost it, whether you captured theirs, and how much crew you
+    /// lost.
+    fn simulate<R: Rng>(rng: &mut R, boarding: &Boarding, strategy: &dyn Strategy)
+                        -> (bool, bool, u32) {
+        let (mut u, mut m) = (boarding.ucrew, boarding.mcrew);
+        while u > 0 && m > 0 {
+            let situation = Situation { ucrew: u, mcrew: m,
+                                        player_attack_odds: &boarding.player_attack_odds,
+                                        player_defense_odds: &boarding.player_defense_odds,
+                                        enemy_rule: boarding.enemy_rule };
+            let you_attack = u >= 2 && strategy.decide(&situation).action == Action::Attack;
+            let enemy_attacks = m >= 2 && situation.enemy_attacks();
+            if !you_attack && !enemy_attacks { break }
+            let (upow, tpow) = round_powers(u, m, you_attack, enemy_attacks,
+                                            &boarding.player_attack_odds,
+                                            &boarding.player_defense_odds);
+            if rng.gen::<f64>() * tpow >= upow { u -= 1 } else { m -= 1 }
+        }
+        (u == 0, m == 0, boarding.ucrew - u)
+    }
+
+    #[test]
+    fn matches_simulation() {
+        const TRIALS: usize = 200_000;
+        let you = Side::new(8, Government::new(1.0, 2.0), vec![("Fragmentation Grenades".to_owned(), Weapon::new(1.3, 0.3), 3)]);
+        let them = Side::new(12, Government::new(1.4, 2.6), Vec::new());
+        let boarding = Scenario::new(you, them).prepare().unwrap();
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(OneAtATime), Box::new(AlwaysAttack)];
+        for strategy in strategies.iter() {
+            let solution = boarding.defense(strategy.as_ref());
+            let mut rng = work_unit_rng(1, 0);
+            let (mut lost, mut captured, mut casualties) = (0, 0, Vec::with_capacity(TRIALS));
+            for _ in 0 .. TRIALS {
+                let (l, c, n) = simulate(&mut rng, &boarding, strategy.as_ref());
+                lost += l as usize;
+                captured += c as usize;
+                casualties.push(n as f64);
+            }
+            // four standard errors, so this only fails if something's wrong
+            for &(what, count, exact) in [("loss", lost, solution.loss_odds(8, 12)),
+                                          ("capture", captured, solution.capture_odds(8, 12))].iter() {
+                let rate = count as f64 / TRIALS as f64;
+                let tolerance = 4.0 * (exact * (1.0 - exact) / TRIALS as f64).sqrt();
+                assert!((rate - exact).abs() < tolerance, "{} {}: simulated {}, exact {}",
+                        strategy.name(), what, rate, exact);
+            }
+            let mean = casualties.iter().sum::<f64>() / TRIALS as f64;
+            let variance = casualties.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (TRIALS - 1) as f64;
+            let exact = solution.casualties(8, 12);
+            assert!((mean - exact).abs() < 4.0 * (variance / TRIALS as f64).sqrt(),
+                    "{} casualties: simulated {}, exact {}", strategy.name(), mean, exact);
+        }
+    }
+}
@@ -26,15 +26,17 @@ impl DoomsdayClock {
             }
         }
     }
-    /// Returns true if we successfully claimed a tick.
-    pub fn tick(&self) -> bool {
-        self.raw_tick().0
-    }
-    pub fn tick_loudly(&self) -> bool {
-        let ret = self.raw_tick();
-        if ret.0 {
-            eprint!("\r{}  \r", ret.1);
+    /// Returns the index of the tick we claimed, if we successfully claimed
+    /// one. Every index from 0 up to (but not including) the initial count
+    /// is handed out exactly once.
+    pub fn tick(&self) -> Option<usize> {
+        match self.raw_tick() {
+            (true, x) => Some(x - 1),
+            _ => None,
         }
-        ret.0
+    }
+    /// Stops handing out ticks, as though every one had been claimed.
+    pub fn stop(&self) {
+        self.counter.store(0, Ordering::SeqCst);
     }
 }
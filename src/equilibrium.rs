@@ -0,0 +1,191 @@
+use crate::simulation::{round_powers, round_is_fought};
+use crate::capture_odds::CaptureOdds;
+use crate::state_table::StateTable;
+use crate::strategy::Action;
+
+/// The value of a boarding action when both sides choose whether to attack
+/// at the same time each round, and the enemy is out to stop you rather than
+/// following the game's AI. Whatever the enemy does, playing your side of
+/// this gives you at least these odds.
+///
+/// Each round is a two-by-two zero-sum game, whose payoffs are the odds of
+/// victory from the states it can lead to. As with `OptimalPolicy`, those are
+/// always smaller states, so one pass from the bottom up solves everything.
+pub struct Equilibrium {
+    victory_odds: StateTable<f64>,
+    player_attack_probability: StateTable<f64>,
+    enemy_attack_probability: StateTable<f64>,
+}
+
+/// Solves a zero-sum game with payoffs `[[a, b], [c, d]]` to the row
+/// player, who wants them high. Rows are attack and defend for you, and
+/// columns are attack and defend for them. Returns the value of the game and
+/// how often each side should attack.
+fn solve_round(a: f64, b: f64, c: f64, d: f64) -> (f64, f64, f64) {
+    let maximin = a.min(b).max(c.min(d));
+    let minimax = a.max(c).min(b.max(d));
+    if maximin >= minimax {
+        // there's a saddle point, so neither side needs to mix
+        let p = if a.min(b) >= c.min(d) { 1.0 } else { 0.0 };
+        let q = if a.max(c) <= b.max(d) { 1.0 } else { 0.0 };
+        (maximin, p, q)
+    }
+    else {
+        let denominator = a - b - c + d;
+        let value = (a * d - b * c) / denominator;
+        let p = (d - c) / denominator;
+        let q = (d - b) / denominator;
+        (value, p, q)
+    }
+}
+
+impl Equilibrium {
+    pub fn new(ucrew: u32, mcrew: u32,
+               player_attack_odds: &CaptureOdds,
+               player_defense_odds: &CaptureOdds)
+               -> Equilibrium {
+        let mut ret = Equilibrium {
+            victory_odds: StateTable::new(ucrew, mcrew),
+            player_attack_probability: StateTable::new(ucrew, mcrew),
+            enemy_attack_probability: StateTable::new(ucrew, mcrew),
+        };
+        for u in 0 ..= ucrew {
+            for m in 0 ..= mcrew {
+                let (odds, p, q) = if m == 0 { (1.0, 0.0, 0.0) }
+                else if u <= 1 { (0.0, 0.0, 0.0) }
+                else {
+                    let won = ret.victory_odds(u, m - 1);
+                    let lost = ret.victory_odds(u - 1, m);
+                    let payoff = |you_attack, enemy_attacks| {
+                        // if nobody attacks, you go back to your ship
+                        // empty-handed
+                        let action = if you_attack { Action::Attack } else { Action::Defend };
+                        if !round_is_fought(action, enemy_attacks) { return 0.0 }
+                        let (upow, tpow) = round_powers(u, m, you_attack, enemy_attacks,
+                                                        player_attack_odds,
+                                                        player_defense_odds);
+                        let win = upow / tpow;
+                        win * won + (1.0 - win) * lost
+                    };
+                    if m >= 2 {
+                        solve_round(payoff(true, true), payoff(true, false),
+                                    payoff(false, true), payoff(false, false))
+                    }
+                    // with one crew member left, they can only defend, as in
+                    // `DefenseSolution`
+                    else { (payoff(true, false), 1.0, 0.0) }
+                };
+                ret.victory_odds.push(odds);
+                ret.player_attack_probability.push(p);
+                ret.enemy_attack_probability.push(q);
+            }
+        }
+        ret
+    }
+    pub fn victory_odds(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.victory_odds[(ucrew, mcrew)]
+    }
+    /// How often you should attack in this state.
+    pub fn player_attack_probability(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.player_attack_probability[(ucrew, mcrew)]
+    }
+    /// How often the enemy should attack in this state.
+    pub fn enemy_attack_probability(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.enemy_attack_probability[(ucrew, mcrew)]
+    }
+    /// How many states, out of those where the fight isn't over, call for
+    /// either side to mix attacking and defending.
+    pub fn mixed_states(&self) -> usize {
+        self.player_attack_probability.values().iter()
+            .zip(self.enemy_attack_probability.values().iter())
+            .filter(|&(&p, &q)| (p > 0.0 && p < 1.0) || (q > 0.0 && q < 1.0))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn close(a: (f64, f64, f64), b: (f64, f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-12 && (a.1 - b.1).abs() < 1e-12 && (a.2 - b.2).abs() < 1e-12
+    }
+
+    #[test]
+    fn saddle_point() {
+        // attacking is always at least as good for you, and attacking is
+        // better for them once you do
+        let solution = solve_round(0.6, 0.8, 0.3, 0.0);
+        assert!(close(solution, (0.6, 1.0, 1.0)), "{:?}", solution);
+    }
+
+    #[test]
+    fn no_saddle_point() {
+        // each side wants to do the opposite of what the other does, so they
+        // mix until the other side doesn't care
+        let solution = solve_round(0.2, 0.9, 0.7, 0.0);
+        assert!(close(solution, (0.45, 0.5, 9.0 / 14.0)), "{:?}", solution);
+        let (value, p, q) = solution;
+        assert!((0.2 * q + 0.9 * (1.0 - q) - value).abs() < 1e-12);
+        assert!((0.7 * q - value).abs() < 1e-12);
+        assert!((0.2 * p + 0.7 * (1.0 - p) - value).abs() < 1e-12);
+        assert!((0.9 * p - value).abs() < 1e-12);
+    }
+
+    /// Your odds in every state when each side either sticks to one action
+    /// (`Some(true)` to attack) or picks whichever action is best for it
+    /// (`None`). They can't attack with their last crew member.
+    fn pure(boarding: &Boarding, you: Option<bool>, them: Option<bool>) -> Vec<Vec<f64>> {
+        let (ucrew, mcrew) = (boarding.ucrew as usize, boarding.mcrew as usize);
+        let mut odds = vec![vec![0.0; mcrew + 1]; ucrew + 1];
+        for u in 0 ..= ucrew {
+            for m in 0 ..= mcrew {
+                let value = if m == 0 { 1.0 } else if u <= 1 { 0.0 } else {
+                    let payoff = |you_attack: bool, enemy_attacks: bool| {
+                        if !you_attack && !enemy_attacks { return 0.0 }
+                        let (upow, tpow) = round_powers(u as u32, m as u32, you_attack, enemy_attacks,
+                                                        &boarding.player_attack_odds,
+                                                        &boarding.player_defense_odds);
+                        upow / tpow * odds[u][m - 1] + (1.0 - upow / tpow) * odds[u - 1][m]
+                    };
+                    let column = |you_attack| {
+                        let options: Vec<bool> = match them {
+                            _ if m < 2 => vec![false],
+                            Some(x) => vec![x],
+                            None => vec![true, false],
+                        };
+                        options.into_iter().map(|x| payoff(you_attack, x)).fold(1.0, f64::min)
+                    };
+                    match you {
+                        Some(x) => column(x),
+                        None => column(true).max(column(false)),
+                    }
+                };
+                odds[u][m] = value;
+            }
+        }
+        odds
+    }
+
+    #[test]
+    fn between_pure_strategies() {
+        let you = Side::new(10, Government::new(1.0, 2.0), vec![("Fragmentation Grenades".to_owned(), Weapon::new(1.3, 0.3), 4)]);
+        let them = Side::new(8, Government::new(1.4, 2.6), Vec::new());
+        let boarding = Scenario::new(you, them).prepare().unwrap();
+        let equilibrium = boarding.equilibrium();
+        // the worst you can get by sticking to one action, whatever they do
+        let (attack, defend) = (pure(&boarding, Some(true), None), pure(&boarding, Some(false), None));
+        // the best you can get if they stick to one action and you know which
+        let (vs_attack, vs_defend) = (pure(&boarding, None, Some(true)), pure(&boarding, None, Some(false)));
+        for u in 2 ..= boarding.ucrew as usize {
+            for m in 1 ..= boarding.mcrew as usize {
+                let odds = equilibrium.victory_odds(u as u32, m as u32);
+                let worst = attack[u][m].max(defend[u][m]);
+                let best = vs_attack[u][m].min(vs_defend[u][m]);
+                assert!(worst - 1e-12 <= odds && odds <= best + 1e-12,
+                        "{}/{}: {} isn't between {} and {}", u, m, odds, worst, best);
+            }
+        }
+    }
+}
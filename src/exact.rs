@@ -0,0 +1,131 @@
+use crate::simulation::{choose_actions, round_powers, round_is_fought};
+use crate::capture_odds::CaptureOdds;
+use crate::state_table::StateTable;
+use crate::strategy::*;
+
+/// The exact odds of victory, from every state up to a given pair of crew
+/// counts, for a given strategy.
+///
+/// A boarding action is a Markov chain on (ucrew, mcrew), and every step
+/// removes one crew member from one side, so the odds can be computed in a
+/// single pass from the smallest states upward.
+pub struct ExactSolution {
+    victory_odds: StateTable<f64>,
+    casualties: StateTable<f64>,
+}
+
+impl ExactSolution {
+    pub fn new(ucrew: u32, mcrew: u32,
+               player_attack_odds: &CaptureOdds,
+               player_defense_odds: &CaptureOdds,
+               enemy_rule: EnemyRule,
+               strategy: &dyn Strategy)
+               -> ExactSolution {
+        let mut ret = ExactSolution {
+            victory_odds: StateTable::new(ucrew, mcrew),
+            casualties: StateTable::new(ucrew, mcrew),
+        };
+        // dist[j] = probability that, so far this batch, the enemy has lost
+        // j crew (and we have lost the rest)
+        let mut dist = Vec::new();
+        let mut next_dist = Vec::new();
+        for u in 0 ..= ucrew {
+            for m in 0 ..= mcrew {
+                let (odds, casualties) = if m == 0 { (1.0, 0.0) }
+                else if u <= 1 { (0.0, 0.0) }
+                else {
+                    let (decision, enemy_attacks) = choose_actions(u, m, player_attack_odds, player_defense_odds, enemy_rule, strategy);
+                    let you_attack = decision.action == Action::Attack;
+                    let rounds = decision.rounds.max(1);
+                    if !round_is_fought(decision.action, enemy_attacks) {
+                        ret.victory_odds.push(0.0);
+                        ret.casualties.push(0.0);
+                        continue
+                    }
+                    dist.clear();
+                    dist.push(1.0);
+                    let mut won = 0.0;
+                    let mut won_casualties = 0.0;
+                    for round in 0 .. rounds {
+                        next_dist.clear();
+                        next_dist.resize(dist.len() + 1, 0.0);
+                        for (j, &p) in dist.iter().enumerate() {
+                            if p == 0.0 { continue }
+                            let cur_u = u - (round - j as u32);
+                            let cur_m = m - j as u32;
+                            let (upow, tpow) = round_powers(cur_u, cur_m, you_attack, enemy_attacks, player_attack_odds, player_defense_odds);
+                            let win = p * upow / tpow;
+                            if cur_m == 1 {
+                                won += win;
+                                won_casualties += win * (round - j as u32) as f64;
+                            }
+                            else { next_dist[j + 1] += win }
+                            // losing our last crew member can't actually
+                            // happen within a batch, but if it did, it would
+                            // be a defeat
+                            if cur_u > 1 { next_dist[j] += p - win }
+                        }
+                        std::mem::swap(&mut dist, &mut next_dist);
+                    }
+                    dist.iter().enumerate().fold((won, won_casualties), |a, (j, &p)| {
+                        if p == 0.0 { a }
+                        else {
+                            let lost = rounds - j as u32;
+                            let (u, m) = (u - lost, m - j as u32);
+                            (a.0 + p * ret.victory_odds(u, m),
+                             a.1 + p * (lost as f64 + ret.casualties(u, m)))
+                        }
+                    })
+                };
+                ret.victory_odds.push(odds);
+                ret.casualties.push(casualties);
+            }
+        }
+        ret
+    }
+    pub fn victory_odds(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.victory_odds[(ucrew, mcrew)]
+    }
+    /// The crew you can expect to lose before the fight is over, however it
+    /// ends.
+    pub fn casualties(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.casualties[(ucrew, mcrew)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn boarding(ucrew: u32, mcrew: u32) -> Boarding {
+        let you = Side::new(ucrew, Government::new(1.0, 2.0), Vec::new());
+        let them = Side::new(mcrew, Government::new(1.4, 2.6), Vec::new());
+        Scenario::new(you, them).prepare().unwrap()
+    }
+
+    #[test]
+    fn matches_simulation() {
+        const TRIALS: usize = 200_000;
+        // big enough that auto-go fights several rounds at a time, and the
+        // two strategies' odds differ
+        let boarding = boarding(20, 15);
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(AutoGo), Box::new(OneAtATime)];
+        let tally = boarding.run_trials(&mut work_unit_rng(1, 0), TRIALS, &strategies);
+        for (n, strategy) in strategies.iter().enumerate() {
+            let exact = boarding.exact_victory_odds(strategy.as_ref());
+            let rate = tally.victories[n] as f64 / TRIALS as f64;
+            // four standard errors, so this only fails if something's wrong
+            let tolerance = 4.0 * (exact * (1.0 - exact) / TRIALS as f64).sqrt();
+            assert!((rate - exact).abs() < tolerance, "{}: simulated {}, exact {}",
+                    strategy.name(), rate, exact);
+        }
+    }
+
+    #[test]
+    fn edges() {
+        let boarding = boarding(20, 15);
+        let solution = boarding.exact_solution(&AutoGo);
+        for m in 1 ..= 15 { assert_eq!(solution.victory_odds(1, m), 0.0) }
+        for u in 0 ..= 20 { assert_eq!(solution.victory_odds(u, 0), 1.0) }
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::data_file::*;
+use crate::{Weapon, Government};
+
+fn load_attributes(node: &DataNode, attributes: &mut HashMap<String, f64>) {
+    for child in node.children.iter() {
+        if child.size() < 2 { continue }
+        if let Some(value) = child.value(1) {
+            attributes.insert(child.token(0).to_owned(), value);
+        }
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct Outfit {
+    pub name: String,
+    pub attributes: HashMap<String, f64>,
+}
+
+impl Outfit {
+    fn load(node: &DataNode) -> Outfit {
+        let mut attributes = HashMap::new();
+        load_attributes(node, &mut attributes);
+        Outfit { name: node.token(1).to_owned(), attributes }
+    }
+    /// Returns the named attribute, or zero if it isn't present.
+    pub fn get(&self, attribute: &str) -> f64 {
+        self.attributes.get(attribute).cloned().unwrap_or(0.0)
+    }
+    /// Returns this outfit's capture strengths, if it has any.
+    pub fn as_weapon(&self) -> Option<Weapon> {
+        let attack = self.get("capture attack");
+        let defense = self.get("capture defense");
+        if attack > 0.0 || defense > 0.0 { Some(Weapon::new(attack, defense)) }
+        else { None }
+    }
+}
+
+// see Government::Load in Government.cpp
+fn load_government(node: &DataNode, government: &mut Government) {
+    for child in node.children.iter() {
+        match (child.token(0), child.value(1)) {
+            ("crew attack", Some(x)) => government.attack_strength = x,
+            ("crew defense", Some(x)) => government.defense_strength = x,
+            _ => (),
+        }
+    }
+}
+
+/// Reads an `outfits` block, in which each line is an outfit name and an
+/// optional count.
+pub fn load_outfit_list(node: &DataNode) -> Vec<(String, u32)> {
+    node.children.iter().map(|child| {
+        let count = child.value(1).map(|x| x as u32).unwrap_or(1);
+        (child.token(0).to_owned(), count)
+    }).collect()
+}
+
+#[derive(Clone,Debug)]
+pub struct Ship {
+    pub name: String,
+    /// The attributes of the bare hull.
+    pub attributes: HashMap<String, f64>,
+    /// Installed outfits, and how many of each.
+    pub outfits: Vec<(String, u32)>,
+}
+
+impl Ship {
+    // see Ship::Load in Ship.cpp; a variant starts out as a copy of its base
+    // model, and only replaces the parts it specifies
+    fn load(node: &DataNode, base: Option<&Ship>) -> Ship {
+        let name = node.token(node.size() - 1).to_owned();
+        let mut ret = match base {
+            Some(base) => Ship { name, ..base.clone() },
+            None => Ship { name, attributes: HashMap::new(), outfits: Vec::new() },
+        };
+        for child in node.children.iter() {
+            match child.token(0) {
+                "attributes" => {
+                    ret.attributes.clear();
+                    load_attributes(child, &mut ret.attributes);
+                },
+                "add" if child.token(1) == "attributes" => {
+                    let mut extra = HashMap::new();
+                    load_attributes(child, &mut extra);
+                    for (k, v) in extra.into_iter() {
+                        *ret.attributes.entry(k).or_insert(0.0) += v;
+                    }
+                },
+                "outfits" => ret.outfits = load_outfit_list(child),
+                _ => (),
+            }
+        }
+        ret
+    }
+    /// Returns the total of the named attribute over the hull and every
+    /// installed outfit that `data` knows about.
+    pub fn get(&self, attribute: &str, data: &GameData) -> f64 {
+        let hull = self.attributes.get(attribute).cloned().unwrap_or(0.0);
+        self.outfits.iter().fold(hull, |a, (name, count)| {
+            match data.outfits.get(name) {
+                Some(outfit) => a + outfit.get(attribute) * *count as f64,
+                None => a,
+            }
+        })
+    }
+    /// Returns the number of crew this ship carries when the game spawns it:
+    /// its required crew, or its bunks if it doesn't require any.
+    pub fn crew(&self, data: &GameData) -> u32 {
+        let required = self.get("required crew", data);
+        if required >= 1.0 { required as u32 }
+        else { self.get("bunks", data).max(0.0) as u32 }
+    }
+    /// Returns the installed outfits that are capture weapons, with their
+    /// names and counts.
+    pub fn weapons(&self, data: &GameData) -> Vec<(String, Weapon, u32)> {
+        self.outfits.iter().filter_map(|(name, count)| {
+            data.outfits.get(name).and_then(|x| x.as_weapon())
+                .map(|wep| (name.clone(), wep, *count))
+        }).collect()
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct GameData {
+    pub outfits: HashMap<String, Outfit>,
+    pub governments: HashMap<String, Government>,
+    pub ships: HashMap<String, Ship>,
+    /// Problems with the data that didn't stop it from loading, for the
+    /// caller to pass on.
+    pub warnings: Vec<String>,
+}
+
+impl GameData {
+    /// Loads every data file found at the given paths. Later definitions
+    /// replace earlier ones, so plugins should come after the game's own data
+    /// directory.
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<GameData, String> {
+        let mut nodes = Vec::new();
+        for path in paths.iter() {
+            let path = path.as_ref();
+            if let Err(x) = load_path(path, &mut nodes) {
+                return Err(format!("Unable to load data from {}: {}", path.display(), x))
+            }
+        }
+        let mut ret = GameData::default();
+        // variants may come before their base models, so they wait until
+        // every base model has been loaded
+        let mut variants = Vec::new();
+        for node in nodes.iter() {
+            if node.token(0) == "outfit" && node.size() >= 2 {
+                let outfit = Outfit::load(node);
+                ret.outfits.insert(outfit.name.clone(), outfit);
+            }
+            else if node.token(0) == "government" && node.size() >= 2 {
+                // a government that is defined more than once gets the
+                // union of its definitions, as in the game
+                let government = ret.governments
+                    .entry(node.token(1).to_owned())
+                    .or_insert_with(|| Government::new(1.0, 2.0));
+                load_government(node, government);
+            }
+            else if node.token(0) == "ship" && node.size() == 2 {
+                let ship = Ship::load(node, None);
+                ret.ships.insert(ship.name.clone(), ship);
+            }
+            else if node.token(0) == "ship" && node.size() >= 3 {
+                variants.push(node);
+            }
+        }
+        for node in variants.into_iter() {
+            let ship = match ret.ships.get(node.token(1)) {
+                None => {
+                    ret.warnings.push(format!("ship variant \"{}\" has unknown base model \"{}\"",
+                                              node.token(2), node.token(1)));
+                    continue
+                },
+                Some(base) => Ship::load(node, Some(base)),
+            };
+            ret.ships.insert(ship.name.clone(), ship);
+        }
+        Ok(ret)
+    }
+    /// Returns every outfit that has a `capture attack` or `capture defense`
+    /// attribute.
+    pub fn weapons(&self) -> HashMap<String, Weapon> {
+        self.outfits.values().filter_map(|x| {
+            x.as_weapon().map(|wep| (x.name.clone(), wep))
+        }).collect()
+    }
+}
@@ -1,9 +1,18 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
-use crate::{Weapon, Government};
+use capsim::{Weapon, Government, Scenario, Side, WorkPlan};
+use capsim::game_data::{GameData, Ship};
+use capsim::pilot::*;
+use capsim::plan::Hull;
+use capsim::strategy::*;
+use capsim::sweep::*;
+
+use crate::report::Format;
 
 lazy_static! {
-    static ref WEAPONS: HashMap<&'static str, Weapon> = [
+    /// Used when no data paths are given.
+    static ref BUILTIN_WEAPONS: HashMap<&'static str, Weapon> = [
         ("Fragmentation Grenades", Weapon::new(1.3, 0.3)),
         ("Intrusion Countermeasures", Weapon::new(0.0, 60.0)),
         ("Korath Repeater Rifle", Weapon::new(1.6, 2.4)),
@@ -16,22 +25,145 @@ lazy_static! {
     ].iter().cloned().collect();
 }
 
+/// What to work out.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Mode {
+    /// Solve and simulate each strategy, and compare them.
+    Compare,
+    /// Find the best policy against the game's AI.
+    Optimal,
+    /// Find how both sides should play against a perfect opponent.
+    Equilibrium,
+    /// Find the odds of keeping your ship when they board you.
+    Defense,
+    /// Find where the victory odds reach a target along one range.
+    Solve,
+    /// Find the best weapons to buy with a budget.
+    Optimize,
+    /// Fill a hull's free space with whatever best helps capture.
+    Plan,
+    /// Try every ship in the data files.
+    Targets,
+}
+
+/// How to sort the table of targets.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum TargetOrder {
+    /// Best victory odds first.
+    Odds,
+    /// Fewest casualties first.
+    Casualties,
+    /// Least crew first.
+    Crew,
+    /// By name.
+    Ship,
+}
+
+impl FromStr for TargetOrder {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "odds" => Ok(TargetOrder::Odds),
+            "casualties" => Ok(TargetOrder::Casualties),
+            "crew" => Ok(TargetOrder::Crew),
+            "ship" => Ok(TargetOrder::Ship),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compare" => Ok(Mode::Compare),
+            "optimal" => Ok(Mode::Optimal),
+            "equilibrium" => Ok(Mode::Equilibrium),
+            "defense" => Ok(Mode::Defense),
+            "solve" => Ok(Mode::Solve),
+            "optimize" => Ok(Mode::Optimize),
+            "plan" => Ok(Mode::Plan),
+            "targets" => Ok(Mode::Targets),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Invocation {
+    pub mode: Mode,
     pub ucrew: u32,
     pub mcrew: u32,
     pub ugov: Government,
     pub mgov: Government,
-    pub uwep: Vec<(Weapon, u32)>,
-    pub mwep: Vec<(Weapon, u32)>,
+    /// Your weapons, with their names and counts.
+    pub uwep: Vec<(String, Weapon, u32)>,
+    pub mwep: Vec<(String, Weapon, u32)>,
     pub ucount: usize,
     pub icount: usize,
     pub tcount: usize,
     pub verbose: bool,
     pub force_threaded: bool,
+    pub exact_only: bool,
+    /// As a fraction, e.g. 0.95.
+    pub confidence: f64,
+    /// The target confidence interval half-width, in percentage points.
+    pub precision: Option<f64>,
+    pub seed: Option<u64>,
+    /// How much crew you need left after a victory, if you said.
+    pub prize_crew: Option<u32>,
+    /// With `Mode::Optimize`, how much there is to spend.
+    pub budget: Option<f64>,
+    /// With `Mode::Optimize`, what each weapon costs. If this is empty, they
+    /// all cost 1.
+    pub costs: Vec<(String, f64)>,
+    /// With `Mode::Plan`, the room your ship has for more outfits and crew.
+    pub hull: Option<Hull>,
+    /// With `Mode::Plan`, whether weapons may go in the cargo hold too.
+    pub ucargo: bool,
+    /// With `Mode::Plan` and `Mode::Targets`, the loaded data files.
+    pub data: Option<GameData>,
+    /// With `Mode::Targets`, how to sort the results.
+    pub sort: TargetOrder,
+    /// With `Mode::Solve`, the victory odds to aim for, as a fraction.
+    pub target: f64,
+    pub format: Format,
+    /// The enemy's rule. If several were given, this is the first, and
+    /// there's an axis for the rest.
+    pub enemy_rule: EnemyRule,
+    /// The strategies to compare, in the order they should be reported.
+    pub strategies: Vec<Box<dyn Strategy>>,
+    /// Every weapon we know about, by name.
+    pub weapons: HashMap<String, Weapon>,
+    /// If there are any of these, we're doing a sweep, and the values above
+    /// are only the starting point.
+    pub axes: Vec<Axis>,
+}
+
+fn builtin_weapons() -> HashMap<String, Weapon> {
+    BUILTIN_WEAPONS.iter().map(|(k,v)| (k.to_string(), *v)).collect()
 }
 
-fn print_usage(autonym: &str, opts: &getopts::Options) {
+impl Invocation {
+    /// `get_invocation` makes sure both sides have crew, so this can always
+    /// be prepared.
+    pub fn scenario(&self) -> Scenario {
+        let mut ret = Scenario::new(Side::new(self.ucrew, self.ugov, self.uwep.clone()),
+                                    Side::new(self.mcrew, self.mgov, self.mwep.clone()));
+        ret.enemy_rule = self.enemy_rule;
+        ret
+    }
+    pub fn work_plan(&self) -> WorkPlan {
+        WorkPlan {
+            icount: self.icount,
+            tcount: self.tcount,
+            force_threaded: self.force_threaded,
+        }
+    }
+}
+
+fn print_usage(autonym: &str, opts: &getopts::Options,
+               weapons: &HashMap<String, Weapon>) {
     let brief = format!("Usage: {} options...", autonym);
     print!("{}", opts.usage(&brief));
     print!(r#"
@@ -46,7 +178,7 @@ World-Ship:
 
 Here are the weapons I know about:
 "#, autonym);
-    let mut weps: Vec<(&&str, &Weapon)> = WEAPONS.iter().collect();
+    let mut weps: Vec<(&String, &Weapon)> = weapons.iter().collect();
     weps.sort_by(|a,b| -> std::cmp::Ordering {
         a.0.partial_cmp(b.0).unwrap()
     });
@@ -56,101 +188,369 @@ Here are the weapons I know about:
     }
 }
 
-fn parse_weps(s: Vec<String>) -> Result<Vec<(Weapon, u32)>, ()> {
+fn parse_gov(s: &str, data: Option<&GameData>) -> Result<Government, ()> {
+    if let Ok(x) = s.parse() { return Ok(x) }
+    if let Some(x) = data.and_then(|x| x.governments.get(s)) { return Ok(*x) }
+    if s.contains('/') { return Err(()) }
+    eprintln!("Unknown government: {}", s);
+    match data {
+        None => eprintln!("(Governments can only be given by name if -data is specified.)"),
+        Some(data) => {
+            let mut govs: Vec<(&String, &Government)> = data.governments.iter().collect();
+            govs.sort_by(|a,b| a.0.cmp(b.0));
+            eprintln!("Here are the governments I know about:");
+            for gov in govs {
+                eprintln!("\t{} (attack {}, defense {})", gov.0,
+                          gov.1.attack_strength, gov.1.defense_strength);
+            }
+        },
+    }
+    Err(())
+}
+
+/// Any counts given as ranges are added to `ranges`, along with the index of
+/// their weapon and its name, and the start of the range is used as the
+/// count.
+fn parse_weps(s: Vec<String>, weapons: &HashMap<String, Weapon>,
+              ranges: &mut Vec<(usize, String, Range)>)
+              -> Result<Vec<(String, Weapon, u32)>, ()> {
     let mut ret = Vec::with_capacity(s.len());
     for s in s.iter() {
         let s: Vec<&str> = s.split(" x ").collect();
         if s.len() != 2 { return Err(()) }
-        let wep = match WEAPONS.get(s[0]) {
+        let wep = match weapons.get(s[0]) {
             None => { eprintln!("Unknown weapon: {}", s[0]); return Err(()) },
             Some(x) => x,
         };
-        let count = match s[1].parse() {
-            Err(_) => return Err(()),
-            Ok(x) => x,
+        let count = if s[1].contains("..") {
+            match s[1].parse::<Range>() {
+                Err(_) => return Err(()),
+                Ok(x) => {
+                    ranges.push((ret.len(), s[0].to_owned(), x));
+                    x.start
+                },
+            }
+        }
+        else {
+            match s[1].parse() {
+                Err(_) => return Err(()),
+                Ok(x) => x,
+            }
         };
-        ret.push((*wep, count));
+        ret.push((s[0].to_owned(), *wep, count));
     }
     Ok(ret)
 }
 
+/// Returns the capture weapons a saved ship would fight with, complaining
+/// about any outfits whose capture strengths we can't know.
+fn saved_ship_weps(ship: &SavedShip, weapons: &HashMap<String, Weapon>,
+                   data: Option<&GameData>, use_cargo: bool)
+                   -> Vec<(String, Weapon, u32)> {
+    let mut ret = Vec::new();
+    let mut unknown = Vec::new();
+    let mut cargo_weps = 0;
+    for &(ref name, count) in ship.outfits.iter() {
+        match weapons.get(name) {
+            Some(wep) => ret.push((name.clone(), *wep, count)),
+            None => {
+                if data.map(|x| !x.outfits.contains_key(name)).unwrap_or(true) {
+                    unknown.push(name.as_str());
+                }
+            },
+        }
+    }
+    for &(ref name, count) in ship.cargo_outfits.iter() {
+        if let Some(wep) = weapons.get(name) {
+            if use_cargo { ret.push((name.clone(), *wep, count)) }
+            else { cargo_weps += count }
+        }
+    }
+    if !unknown.is_empty() {
+        if data.is_some() {
+            eprintln!("Warning: these outfits on \"{}\" aren't in the loaded data files, so any capture strength they have is being ignored:", ship.name);
+        }
+        else {
+            eprintln!("Warning: without -data, only the built-in weapons are known. These outfits on \"{}\" are being ignored, even if they would help with capturing:", ship.name);
+        }
+        for name in unknown { eprintln!("\t{}", name) }
+    }
+    if cargo_weps > 0 {
+        eprintln!("Note: {} capture weapons in \"{}\"'s cargo hold are not being counted, since the game only uses installed outfits. Specify -ucargo to count them anyway.", cargo_weps, ship.name);
+    }
+    ret
+}
+
 pub fn get_invocation() -> Option<Invocation> {
     let args: Vec<String> = std::env::args().collect();
     let autonym = args[0].clone();
     let default_thread_count = num_cpus::get();
     let mut opts = getopts::Options::new();
     opts.long_only(true);
+    opts.optopt("", "mode", "Specify what to work out. 'compare' solves and simulates each strategy. 'optimal' finds the policy that gives you the best odds against the game's AI, and lists the situations where it disagrees with the game's odds. 'equilibrium' assumes the enemy chooses whether to attack as cleverly as you do, and finds the odds you can guarantee no matter what it does. 'defense' has the enemy board you instead, and finds your odds of losing your ship and which weapons would best keep it safe. 'solve' takes exactly one range, e.g. '-ucrew 100..800' or '-uwep \"Tuning Rifle x 0..400\"', and finds the smallest value in it (or for -mcrew and -mwep, the largest) that gives at least -target victory odds. 'optimize' spends -budget on whichever weapons give the best victory odds with the first -strategy, on top of any -uwep you already have. 'plan' fills the free outfit and cargo space on your ship (from -uhull or -usave) with whichever capture weapons and bunks give the best victory odds with the first -strategy, then picks the crew, from the ship's required crew up to every bunk filled, that does best. Weapons only go in the cargo hold with -ucargo; otherwise the cargo space is only for outfits that take it up when installed. With -ucrew, the crew is fixed instead. 'targets' solves a boarding action against every ship in the data files, crewed by each government (governments whose crews are equally strong are tried once, together), and lists your odds and expected casualties with the best -strategy against each. Default is 'compare'.", "MODE");
     opts.optopt("", "ucount", "Specify the number of work units to run. Default is '100'.", "COUNT");
     opts.optopt("", "icount", "Specify the number of iterations per work unit. Larger values are less granular but more efficient. Default is '100'.", "COUNT");
     opts.optopt("", "tcount", &format!("Specify the number of threads to use. Default is {}, which is how many CPUs this computer seems to have.", default_thread_count), "COUNT");
-    opts.reqopt("", "ucrew", "Specify the starting crew quantity on the player's ship. Required.", "CREW");
-    opts.reqopt("", "mcrew", "Specify the starting crew quantity on the enemy's ship. Required.", "CREW");
-    opts.optopt("", "ugov", "Specify the player's government's intrinsic attack and defense strengths. The default is '1.0/2.0', the only value this will ever have in vanilla.", "ATTACK/DEFENSE");
-    opts.optopt("", "mgov", "Specify the enemy government's intrinsic attack and defense strengths. The default is '1.0/2.0', the most common values. Alpha and Korath governments have higher values.", "ATTACK/DEFENSE");
-    opts.optmulti("", "uwep", "Specify a type of weapon on the player's ship, e.g. 'Laser Rifle x 47'. This option may be specified more than once.", "WEAPON x COUNT");
-    opts.optmulti("", "mwep", "Specify a type of weapon on the enemy's ship, e.g. 'Korath Repeater Rifle x 150'. This option may be specified more than once.", "WEAPON x COUNT");
+    opts.optopt("", "confidence", "Specify the confidence level, as a percentage, of the confidence intervals given for the victory rates. Default is '95'.", "PERCENT");
+    opts.optopt("", "precision", "Instead of running a fixed number of work units, keep running them until both victory rates' confidence intervals are no more than this many percentage points either side of the estimate, e.g. '0.1'. This is checked after every work unit, so -icount sets how finely the run can stop, and -ucount is ignored.", "POINTS");
+    opts.optopt("", "target", "With -mode solve, the victory odds to aim for, as a percentage. Default is '90'.", "PERCENT");
+    opts.optopt("", "budget", "With -mode optimize, how much there is to spend on weapons. Required for -mode optimize. Unless -cost is given, this is a number of weapons.", "AMOUNT");
+    opts.optmulti("", "cost", "With -mode optimize, what one of a weapon costs, in the same units as -budget, e.g. 'Nerve Gas=1' for outfit space or 'Nerve Gas=15800' for credits. This option may be specified more than once. If it is given at all, only weapons with a cost are considered.", "WEAPON=COST");
+    opts.optopt("", "free-space", "With -mode plan, how much free outfit space your ship has. Default is however much its outfits leave.", "SPACE");
+    opts.optopt("", "free-cargo", "With -mode plan, how much free cargo space your ship has. Default is however much its outfits leave.", "SPACE");
+    opts.optopt("", "bunks", "With -mode plan, how many bunks your ship has before any more are added. Default is however many its hull and outfits give it.", "BUNKS");
+    opts.optopt("", "sort", "With -mode targets, how to sort the results: 'odds' (best first), 'casualties' (fewest first), 'crew' (least first), or 'ship'. Default is 'odds'.", "ORDER");
+    opts.optopt("", "prize-crew", "Also report the odds of winning with at least this much crew left, e.g. enough to man the ship you're capturing.", "CREW");
+    opts.optopt("", "seed", "Specify the random seed. The same seed gives the same results, no matter how many threads are used. Default is a random seed, which is printed with the results so that the run can be repeated.", "SEED");
+    opts.optopt("", "format", "Specify the output format: 'markdown', 'json', or 'csv'. JSON and CSV output have a fixed set of fields, with odds given as fractions rather than percentages. CSV output is one 'field,value' row per fact, except for sweeps and -mode targets, which give a table. Default is 'markdown'.", "FORMAT");
+    opts.optmulti("", "strategy", "Specify a strategy to compare: 'auto-go', 'one-at-a-time', 'always-attack', 'attack-above:ODDS' (defend until the game's odds of capturing them exceed ODDS, a fraction), or 'auto-go-above:CREW' (auto-go while you have at least CREW crew, then one-at-a-time). Add '+retreat:CREW' to any of these to go back to your ship once you have fewer than CREW crew left, or '+retreat-odds:ODDS' to go back once the game's odds of capturing them drop below ODDS, e.g. 'auto-go+retreat:50'. This option may be specified more than once. Default is 'auto-go' and 'one-at-a-time'.", "STRATEGY");
+    opts.optmulti("", "enemy", "Specify how the enemy decides whether to attack: 'game' (the game's AI, which attacks when it has better than even odds of capturing you), 'always-attack', 'always-defend', 'threshold:ODDS' (attack when its odds of capturing you are better than ODDS, a fraction), or 'mirror' (attack when its odds of capturing you are better than yours of capturing it). If this is specified more than once, sweep over all of the given rules. Default is 'game'.", "RULE");
+    opts.optflag("", "exact", "Only print the exact victory odds, which are always computed, and skip the simulation.");
+    opts.optopt("", "ucrew", "Specify the starting crew quantity on the player's ship. Required, unless -usave is given. This may be a range, e.g. '300..600:20', to sweep over every value in that range.", "CREW");
+    opts.optopt("", "mcrew", "Specify the starting crew quantity on the enemy's ship. Required, unless -mship is given. This may be a range, like -ucrew.", "CREW");
+    opts.optopt("", "ugov", "Specify the player's government's intrinsic attack and defense strengths. The default is '1.0/2.0', the only value this will ever have in vanilla. If -data is specified, this may also be the name of a government.", "ATTACK/DEFENSE");
+    opts.optopt("", "mgov", "Specify the enemy government's intrinsic attack and defense strengths. The default is '1.0/2.0', the most common values. Alpha and Korath governments have higher values. If -data is specified, this may also be the name of a government, e.g. 'Korath'.", "ATTACK/DEFENSE");
+    opts.optmulti("", "uwep", "Specify a type of weapon on the player's ship, e.g. 'Laser Rifle x 47'. This option may be specified more than once. The count may be a range, like -ucrew.", "WEAPON x COUNT");
+    opts.optmulti("", "mwep", "Specify a type of weapon on the enemy's ship, e.g. 'Korath Repeater Rifle x 150'. This option may be specified more than once. The count may be a range, like -ucrew.", "WEAPON x COUNT");
+    opts.optopt("", "mship", "Take the enemy's crew and weapons from a ship definition in the data files, e.g. 'Korath World-Ship'. Requires -data. Any -mcrew or -mwep options override the corresponding part of the ship.", "NAME");
+    opts.optopt("", "uhull", "Take the player's crew and weapons from a ship definition in the data files, like -mship. Requires -data. Any -ucrew or -uwep options override the corresponding part of the ship.", "NAME");
+    opts.optopt("", "usave", "Take the player's crew and weapons from a pilot save file. Any -ucrew or -uwep options override the corresponding part of the ship.", "PATH");
+    opts.optopt("", "uship", "With -usave, use the ship with this name instead of the flagship.", "NAME");
+    opts.optflag("", "ucargo", "With -usave, also count capture weapons that are in the ship's cargo hold, rather than installed. With -mode plan, also consider carrying capture weapons in the cargo hold, each taking up as much cargo space as its mass.");
+    opts.optmulti("", "data", "Load weapons, governments and ships from an Endless Sky data directory (or a single data file). Every outfit with a \"capture attack\" or \"capture defense\" attribute becomes a weapon. This option may be specified more than once; give the game's data directory first, then any plugins. If it is not given, a small built-in weapon table is used.", "PATH");
     let matches = match opts.parse(&args[1..]) {
         Ok(x) => x,
         Err(x) => {
-            eprintln!("{}", x.to_string());
-            print_usage(&autonym, &opts);
+            eprintln!("{}", x);
+            print_usage(&autonym, &opts, &builtin_weapons());
             return None
         }
     };
-    let ucrew = match matches.opt_str("ucrew").unwrap().parse() {
-        Err(_) | Ok(0) => {
-            eprintln!("ucrew value must be a positive integer");
-            print_usage(&autonym, &opts);
+    let data_paths = matches.opt_strs("data");
+    let data = if data_paths.is_empty() { None }
+    else {
+        match GameData::load(&data_paths) {
+            Err(x) => {
+                eprintln!("{}", x);
+                return None
+            },
+            Ok(x) => {
+                for warning in x.warnings.iter() {
+                    eprintln!("Warning: {}", warning);
+                }
+                Some(x)
+            },
+        }
+    };
+    let weapons = match data.as_ref() {
+        None => builtin_weapons(),
+        Some(x) => x.weapons(),
+    };
+    let mode = match matches.opt_get_default("mode", Mode::Compare) {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("mode value must be 'compare', 'optimal', 'equilibrium', 'defense', 'solve', 'optimize', 'plan', or 'targets'");
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
-        Ok(x) => x,
     };
-    let mcrew = match matches.opt_str("mcrew").unwrap().parse() {
-        Err(_) | Ok(0) => {
-            eprintln!("mcrew value must be a positive integer");
-            print_usage(&autonym, &opts);
+    let mut axes = Vec::new();
+    let mut uwep_ranges = Vec::new();
+    let mut mwep_ranges = Vec::new();
+    let pilot = match matches.opt_str("usave") {
+        None => None,
+        Some(path) => match Pilot::load(std::path::Path::new(&path)) {
+            Err(x) => {
+                eprintln!("{}", x);
+                return None
+            },
+            Ok(x) => Some(x),
+        },
+    };
+    let uship = match (pilot.as_ref(), matches.opt_str("uship")) {
+        (None, None) => None,
+        (None, Some(_)) => {
+            eprintln!("uship requires -usave");
             return None
         },
-        Ok(x) => x,
+        (Some(pilot), None) => match pilot.flagship() {
+            None => {
+                eprintln!("The save file doesn't seem to have a flagship");
+                return None
+            },
+            Some(x) => Some(x),
+        },
+        (Some(pilot), Some(name)) => match pilot.ship_named(&name) {
+            None => {
+                eprintln!("The save file has no ship named \"{}\". Here are the ships it has:", name);
+                for ship in pilot.ships.iter() {
+                    eprintln!("\t{} ({})", ship.name, ship.model);
+                }
+                return None
+            },
+            Some(x) => Some(x),
+        },
+    };
+    let uhull = match matches.opt_str("uhull") {
+        None => None,
+        Some(_) if pilot.is_some() => {
+            eprintln!("uhull and usave can't both be given");
+            return None
+        },
+        Some(name) => match data.as_ref().map(|x| x.ships.get(&name)) {
+            None => {
+                eprintln!("uhull requires -data");
+                return None
+            },
+            Some(None) => {
+                eprintln!("Unknown ship: {}", name);
+                return None
+            },
+            Some(Some(x)) => Some(x.clone()),
+        },
+    };
+    // a saved ship is its model's hull with whatever outfits it has now
+    let uhull = match (uhull, uship, data.as_ref()) {
+        (None, Some(saved), Some(data)) => data.ships.get(&saved.model).map(|model| {
+            Ship { name: saved.name.clone(), outfits: saved.outfits.clone(), ..model.clone() }
+        }),
+        (x, _, _) => x,
+    };
+    let ucrew = match matches.opt_str("ucrew") {
+        None => match (uship, uhull.as_ref()) {
+            (Some(ship), _) => ship.crew,
+            (None, Some(ship)) => ship.crew(data.as_ref().unwrap()),
+            (None, None) => {
+                eprintln!("Required option 'ucrew' missing.");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        },
+        Some(x) if x.contains("..") => match x.parse::<Range>() {
+            Ok(x) if x.start > 0 => {
+                axes.push(Axis { name: "ucrew".to_owned(), parameter: Parameter::UCrew, values: x.values() });
+                x.start
+            },
+            _ => {
+                eprintln!("ucrew range must be in the form START..END or START..END:STEP, and can't include 0");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        },
+        Some(x) => match x.parse() {
+            Err(_) | Ok(0) => {
+                eprintln!("ucrew value must be a positive integer");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+            Ok(x) => x,
+        },
+    };
+    if ucrew == 0 {
+        eprintln!("Your ship has no crew; specify -ucrew");
+        return None
+    }
+    let mship = match matches.opt_str("mship") {
+        None => None,
+        Some(name) => match data.as_ref().map(|x| x.ships.get(&name)) {
+            None => {
+                eprintln!("mship requires -data");
+                return None
+            },
+            Some(None) => {
+                eprintln!("Unknown ship: {}", name);
+                return None
+            },
+            Some(Some(x)) => Some(x),
+        },
+    };
+    let mcrew = match matches.opt_str("mcrew") {
+        None => match mship {
+            Some(ship) => ship.crew(data.as_ref().unwrap()),
+            // every target brings its own crew
+            None if mode == Mode::Targets => 1,
+            None => {
+                eprintln!("Required option 'mcrew' missing.");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        },
+        Some(x) if x.contains("..") => match x.parse::<Range>() {
+            Ok(x) if x.start > 0 => {
+                axes.push(Axis { name: "mcrew".to_owned(), parameter: Parameter::MCrew, values: x.values() });
+                x.start
+            },
+            _ => {
+                eprintln!("mcrew range must be in the form START..END or START..END:STEP, and can't include 0");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        },
+        Some(x) => match x.parse() {
+            Err(_) | Ok(0) => {
+                eprintln!("mcrew value must be a positive integer");
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+            Ok(x) => x,
+        },
     };
-    let ugov = match matches.opt_str("ugov").as_ref().map(|x| x.as_str()).unwrap_or("1/2").parse() {
+    if mcrew == 0 {
+        eprintln!("The enemy ship has no crew; specify -mcrew");
+        return None
+    }
+    let ugov = match parse_gov(matches.opt_str("ugov").as_deref().unwrap_or("1/2"), data.as_ref()) {
         Err(_) => {
-            eprintln!("ugov value must be two positive floats separated by /");
-            print_usage(&autonym, &opts);
+            eprintln!("ugov value must be a government name, or two positive floats separated by /");
             return None
         },
         Ok(x) => x,
     };
-    let mgov = match matches.opt_str("mgov").as_ref().map(|x| x.as_str()).unwrap_or("1/2").parse() {
+    let mgov = match parse_gov(matches.opt_str("mgov").as_deref().unwrap_or("1/2"), data.as_ref()) {
         Err(_) => {
-            eprintln!("mgov value must be two positive floats separated by /");
-            print_usage(&autonym, &opts);
+            eprintln!("mgov value must be a government name, or two positive floats separated by /");
             return None
         },
         Ok(x) => x,
     };
-    let uwep = match parse_weps(matches.opt_strs("uwep")) {
+    let uwep = match parse_weps(matches.opt_strs("uwep"), &weapons, &mut uwep_ranges) {
         Err(_) => {
-            eprintln!("uwep values must be in the form of \"WEAPON x COUNT\".");
+            eprintln!("uwep values must be in the form of \"WEAPON x COUNT\" or \"WEAPON x START..END:STEP\".");
             eprintln!("Example: \"Laser Rifle x 47\"");
-            print_usage(&autonym, &opts);
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
         Ok(x) => x,
     };
-    let mwep = match parse_weps(matches.opt_strs("mwep")) {
+    let mwep = match parse_weps(matches.opt_strs("mwep"), &weapons, &mut mwep_ranges) {
         Err(_) => {
-            eprintln!("mwep values must be in the form of \"WEAPON x COUNT\".");
+            eprintln!("mwep values must be in the form of \"WEAPON x COUNT\" or \"WEAPON x START..END:STEP\".");
             eprintln!("Example: \"Korath Repeater Rifle x 150\"");
-            print_usage(&autonym, &opts);
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
         Ok(x) => x,
     };
+    let uwep = match (uship, uhull.as_ref()) {
+        _ if matches.opt_present("uwep") => uwep,
+        (Some(ship), _) => saved_ship_weps(ship, &weapons, data.as_ref(), matches.opt_present("ucargo")),
+        (None, Some(ship)) => ship.weapons(data.as_ref().unwrap()),
+        (None, None) => uwep,
+    };
+    let mwep = match mship {
+        Some(ship) if !matches.opt_present("mwep") => ship.weapons(data.as_ref().unwrap()),
+        _ => mwep,
+    };
+    for (n, name, range) in uwep_ranges.into_iter() {
+        axes.push(Axis { name: format!("uwep {}", name), parameter: Parameter::UWeapon(n), values: range.values() });
+    }
+    for (n, name, range) in mwep_ranges.into_iter() {
+        axes.push(Axis { name: format!("mwep {}", name), parameter: Parameter::MWeapon(n), values: range.values() });
+    }
     let ucount = match matches.opt_get_default("ucount", 100) {
         Err(_) | Ok(0) => {
             eprintln!("ucount value must be a positive integer");
-            print_usage(&autonym, &opts);
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
         Ok(x) => x,
@@ -158,7 +558,7 @@ pub fn get_invocation() -> Option<Invocation> {
     let icount = match matches.opt_get_default("icount", 100) {
         Err(_) | Ok(0) => {
             eprintln!("icount value must be a positive integer");
-            print_usage(&autonym, &opts);
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
         Ok(x) => x,
@@ -166,14 +566,195 @@ pub fn get_invocation() -> Option<Invocation> {
     let tcount = match matches.opt_get_default("tcount", default_thread_count) {
         Err(_) | Ok(0) => {
             eprintln!("tcount value must be a positive integer");
-            print_usage(&autonym, &opts);
+            print_usage(&autonym, &opts, &weapons);
             return None
         },
         Ok(x) => x,
     };
+    let confidence = match matches.opt_get_default("confidence", 95.0) {
+        Ok(x) if x > 0.0 && x < 100.0 => x / 100.0,
+        _ => {
+            eprintln!("confidence value must be a percentage between 0 and 100, exclusive");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let precision = match matches.opt_get::<f64>("precision") {
+        Ok(None) => None,
+        Ok(Some(x)) if x > 0.0 => Some(x),
+        _ => {
+            eprintln!("precision value must be a positive number");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let target = match matches.opt_get_default("target", 90.0) {
+        Ok(x) if (0.0 ..= 100.0).contains(&x) => x / 100.0,
+        _ => {
+            eprintln!("target value must be a percentage between 0 and 100");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let budget = match matches.opt_get::<f64>("budget") {
+        Ok(None) if mode == Mode::Optimize => {
+            eprintln!("optimize requires -budget");
+            return None
+        },
+        Ok(x) if x.map(|x| x >= 0.0).unwrap_or(true) => x,
+        _ => {
+            eprintln!("budget value must be a non-negative number");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let mut costs = Vec::new();
+    for s in matches.opt_strs("cost") {
+        let cost = s.rfind('=').and_then(|x| {
+            let name = s[..x].trim();
+            match s[x+1..].trim().parse::<f64>() {
+                Ok(cost) if cost > 0.0 && weapons.contains_key(name) => Some((name.to_owned(), cost)),
+                _ => None,
+            }
+        });
+        match cost {
+            Some(x) => costs.push(x),
+            None => {
+                eprintln!("cost values must be in the form of \"WEAPON=COST\", with a known weapon and a positive cost: {:?}", s);
+                return None
+            },
+        }
+    }
+    let mut hull = match (uhull.as_ref(), data.as_ref()) {
+        (Some(ship), Some(data)) => Some(Hull::new(ship, data)),
+        _ => None,
+    };
+    if mode == Mode::Plan {
+        match hull.as_mut() {
+            None => {
+                eprintln!("plan requires -data, and either -uhull or -usave with a ship whose model is in the data files");
+                return None
+            },
+            Some(hull) => {
+                for (n, option) in ["free-space", "free-cargo"].iter().enumerate() {
+                    match matches.opt_get::<f64>(option) {
+                        Ok(None) => (),
+                        Ok(Some(x)) if x >= 0.0 => hull.space[n] = x,
+                        _ => {
+                            eprintln!("{} value must be a non-negative number", option);
+                            print_usage(&autonym, &opts, &weapons);
+                            return None
+                        },
+                    }
+                }
+                match matches.opt_get::<u32>("bunks") {
+                    Ok(None) => (),
+                    Ok(Some(x)) => hull.bunks = x,
+                    Err(_) => {
+                        eprintln!("bunks value must be a non-negative integer");
+                        print_usage(&autonym, &opts, &weapons);
+                        return None
+                    },
+                }
+                if hull.bunks == 0 {
+                    eprintln!("Your ship has no bunks; specify -bunks");
+                    return None
+                }
+                hull.required_crew = hull.required_crew.clamp(1, hull.bunks);
+                if matches.opt_present("ucrew") {
+                    if ucrew > hull.bunks {
+                        eprintln!("Your ship only has {} bunks, so it can't carry a crew of {}", hull.bunks, ucrew);
+                        return None
+                    }
+                    hull.crew = Some(ucrew);
+                }
+            },
+        }
+    }
+    if mode == Mode::Targets && data.is_none() {
+        eprintln!("targets requires -data");
+        return None
+    }
+    let sort = match matches.opt_get_default("sort", TargetOrder::Odds) {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("sort value must be 'odds', 'casualties', 'crew', or 'ship'");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let prize_crew = match matches.opt_get::<u32>("prize-crew") {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("prize-crew value must be a non-negative integer");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let seed = match matches.opt_get::<u64>("seed") {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("seed value must be a non-negative integer");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let format = match matches.opt_get_default("format", Format::Markdown) {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("format value must be 'markdown', 'json', or 'csv'");
+            print_usage(&autonym, &opts, &weapons);
+            return None
+        },
+    };
+    let mut strategies = Vec::new();
+    for s in matches.opt_strs("strategy") {
+        match parse_strategy(&s) {
+            Some(x) => strategies.push(x),
+            None => {
+                eprintln!("unknown strategy: {:?}", s);
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        }
+    }
+    if strategies.is_empty() { strategies = default_strategies() }
+    let mut enemy_rules = Vec::new();
+    for s in matches.opt_strs("enemy") {
+        match parse_enemy_rule(&s) {
+            Some(x) => enemy_rules.push(x),
+            None => {
+                eprintln!("unknown enemy rule: {:?}", s);
+                print_usage(&autonym, &opts, &weapons);
+                return None
+            },
+        }
+    }
+    let enemy_rule = enemy_rules.first().cloned().unwrap_or_default();
+    if enemy_rules.len() > 1 {
+        let values = (0 .. enemy_rules.len() as u32).collect();
+        axes.insert(0, Axis { name: "enemy".to_owned(), parameter: Parameter::EnemyRule(enemy_rules), values });
+    }
+    if mode == Mode::Solve {
+        if axes.len() != 1 || !axes[0].parameter.is_numeric() {
+            eprintln!("solve needs exactly one crew or weapon count range to search, e.g. -ucrew 100..800");
+            return None
+        }
+    }
+    else if !axes.is_empty() && mode != Mode::Compare {
+        eprintln!("only -mode compare can be used with a sweep");
+        return None
+    }
+    if !axes.is_empty() && precision.is_some() {
+        eprintln!("precision can't be used with a sweep");
+        return None
+    }
     Some(Invocation{
-        ucrew, mcrew, ugov, mgov, uwep, mwep, ucount, icount, tcount,
+        mode, ucrew, mcrew, ugov, mgov, uwep, mwep, ucount, icount, tcount,
         verbose: true, force_threaded: false,
+        exact_only: matches.opt_present("exact"),
+        ucargo: matches.opt_present("ucargo"),
+        confidence, precision, seed, prize_crew, budget, costs, hull, data, sort, target, format, enemy_rule, strategies, weapons, axes,
     })
 }
 
@@ -0,0 +1,36 @@
+//! Simulates capturing ships in Endless Sky.
+//!
+//! Describe the fight as a `Scenario`, `prepare` it into a `Boarding`, and
+//! then either solve it exactly or simulate as many trials as you like.
+
+extern crate rand;
+
+pub mod power_level;
+pub use power_level::PowerLevel;
+pub use power_level::PowerLevel as Weapon;
+pub use power_level::PowerLevel as Government;
+
+pub mod capture_odds;
+pub mod state_table;
+pub mod doomsday_clock;
+pub mod exact;
+pub mod optimal;
+pub mod equilibrium;
+pub mod defense;
+pub mod statistics;
+
+pub mod strategy;
+pub use strategy::*;
+
+pub mod simulation;
+pub use simulation::*;
+
+pub mod data_file;
+pub mod game_data;
+pub mod pilot;
+
+pub mod sweep;
+pub mod solve;
+pub mod optimize;
+pub mod plan;
+pub mod targets;
@@ -1,126 +1,189 @@
 #[macro_use]
 extern crate lazy_static;
 
+extern crate capsim;
 extern crate getopts;
 extern crate num_cpus;
 extern crate rand;
 
-use rand::{Rng,SeedableRng};
-use std::sync::Arc;
-use std::time::{Duration,Instant};
-
-mod power_level;
-use power_level::*;
-use PowerLevel as Weapon;
-use PowerLevel as Government;
-
-mod capture_odds;
-use capture_odds::*;
+use capsim::*;
+use capsim::statistics::*;
+use capsim::sweep::*;
+use capsim::solve::*;
+use capsim::optimize::*;
+use capsim::plan::*;
+use capsim::targets::*;
 
 mod invocation;
 use invocation::*;
 
-mod doomsday_clock;
-use doomsday_clock::*;
-
-fn calculate_strengths(crew: u32, gov: Government, weps: &[(Weapon, u32)]) -> Vec<PowerLevel> {
-    let length = weps.iter().map(|x| x.1).fold(0, |a,b| a + b);
-    let mut attack_strengths = Vec::with_capacity(length as usize);
-    let mut defense_strengths = Vec::with_capacity(length as usize);
-    for wep in weps {
-        let strength = wep.0 + gov;
-        for _ in 0 .. wep.1 {
-            attack_strengths.push(strength.attack_strength);
-            defense_strengths.push(strength.defense_strength);
-        }
-    }
-    debug_assert_eq!(attack_strengths.len(), defense_strengths.len());
-    while attack_strengths.len() < crew as usize {
-        attack_strengths.push(gov.attack_strength);
-        defense_strengths.push(gov.defense_strength);
-    }
-    // sort in descending order
-    attack_strengths.sort_by(|a,b| b.partial_cmp(a).unwrap());
-    defense_strengths.sort_by(|a,b| b.partial_cmp(a).unwrap());
-    // convert to running sums
-    for n in 1 .. attack_strengths.len() { attack_strengths[n] += attack_strengths[n-1]; }
-    for n in 1 .. defense_strengths.len() { defense_strengths[n] += defense_strengths[n-1]; }
-    attack_strengths.truncate(crew as usize);
-    defense_strengths.truncate(crew as usize);
-    attack_strengths.into_iter().zip(defense_strengths.into_iter()).map(|(a,d)| PowerLevel::new(a,d)).collect()
-}
-
-fn sub_attempt<R: Rng>(rng: &mut R, mut ucrew: u32, mut mcrew: u32,
-                       player_attack_odds: &CaptureOdds,
-                       player_defense_odds: &CaptureOdds,
-                       auto_go: bool)
-                       -> bool {
-    while ucrew > 1 && mcrew > 0 {
-        let uatk_odds = player_attack_odds.capture_odds(ucrew, mcrew);
-        let udef_odds = player_defense_odds.capture_odds(mcrew, ucrew);
-        let enemy_attacks = udef_odds > 0.5;
-        let you_attack = uatk_odds > udef_odds || !enemy_attacks;
-        debug_assert!(enemy_attacks || you_attack);
-        let rounds = if auto_go { (ucrew / 5).max(1) } else { 1 };
-        for _ in 0 .. rounds {
-            if ucrew == 0 || mcrew == 0 { break }
-            let upow = if you_attack {
-                player_attack_odds.attacker_power(ucrew)
-            }
-            else {
-                player_defense_odds.defender_power(ucrew)
-            };
-            let mpow = if enemy_attacks {
-                player_defense_odds.attacker_power(mcrew)
-            }
-            else {
-                player_attack_odds.defender_power(mcrew)
-            };
-            let tpow = upow + mpow;
-            debug_assert!(tpow > 0.0);
-            if rng.gen::<f64>() * tpow >= upow { ucrew -= 1 }
-            else { mcrew -= 1 }
-        }
+mod report;
+use report::*;
+
+/// Summarizes how much crew was left, for the structured report.
+fn crew_report(histogram: &Histogram) -> Value {
+    object(vec![
+        ("mean", histogram.mean().into()),
+        ("p5", histogram.quantile(0.05).into()),
+        ("p25", histogram.quantile(0.25).into()),
+        ("median", histogram.quantile(0.5).into()),
+        ("p75", histogram.quantile(0.75).into()),
+        ("p95", histogram.quantile(0.95).into()),
+        ("histogram", histogram.counts.clone().into()),
+    ])
+}
+
+/// Returns one entry of the structured report's `strategies` list. `sim` is
+/// the simulation results and this strategy's index in them, if there was a
+/// simulation, and the z-score for its confidence interval. The exact rate is
+/// a percentage, the same as `print_advice` wants.
+fn strategy_report(name: &str, sim: Option<(&Tally, usize, f64)>,
+                   exact_victory_rate: f64, prize_crew: Option<u32>) -> Value {
+    let (victories, rate, ci_low, ci_high) = match sim {
+        None => (None, None, None, None),
+        Some((tally, n, z)) => {
+            let victories = tally.victories[n];
+            let (lo, hi) = wilson_interval(victories, tally.trials, z);
+            (Some(victories), Some(victories as f64 / tally.trials as f64), Some(lo), Some(hi))
+        },
+    };
+    object(vec![
+        ("name", name.into()),
+        ("victories", victories.into()),
+        ("victory_rate", rate.into()),
+        ("ci_low", ci_low.into()),
+        ("ci_high", ci_high.into()),
+        ("exact_victory_odds", (exact_victory_rate / 100.0).into()),
+        ("defeats", sim.map(|(tally, n, _)| tally.defeats[n]).into()),
+        ("retreats", sim.map(|(tally, n, _)| tally.retreats[n]).into()),
+        ("mean_retreat_crew", sim.and_then(|(tally, n, _)| tally.mean_retreat_crew(n)).into()),
+        ("crew_after_victory", sim.map(|(tally, n, _)| crew_report(&tally.ucrew_after_victory[n])).into()),
+        ("crew_left", sim.map(|(tally, n, _)| crew_report(&tally.ucrew_left[n])).into()),
+        ("enemy_crew_left", sim.map(|(tally, n, _)| crew_report(&tally.mcrew_left[n])).into()),
+        ("prize_crew_victory_rate", sim.and_then(|(tally, n, _)| prize_crew.map(|k| {
+            tally.ucrew_after_victory[n].at_least(k) as f64 / tally.trials as f64
+        })).into()),
+    ])
+}
+
+fn print_report(format: Format, report: &Value) {
+    match format {
+        Format::Markdown => unreachable!(),
+        Format::Json => print!("{}", to_json(report)),
+        Format::Csv => print!("{}", to_csv(report)),
     }
-    mcrew == 0
 }
 
-fn attempt<R: Rng>(rng: &mut R, ucrew: u32, mcrew: u32,
-                   player_attack_odds: &CaptureOdds,
-                   player_defense_odds: &CaptureOdds)
-                   -> (bool, bool) {
-    (sub_attempt(rng, ucrew, mcrew, player_attack_odds, player_defense_odds, true),
-     sub_attempt(rng, ucrew, mcrew, player_attack_odds, player_defense_odds, false))
+/// Capitalizes a strategy name, for the start of a sentence or a column title.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Takes victory rates as percentages, one per strategy. With two strategies,
+/// the second is compared to the first.
+fn print_advice(names: &[String], victory_rates: &[f64]) {
+    if victory_rates.len() < 2 { return }
+    let mut best = 0;
+    let mut worst = 0;
+    for (n, &rate) in victory_rates.iter().enumerate() {
+        if rate > victory_rates[best] { best = n }
+        if rate < victory_rates[worst] { worst = n }
+    }
+    if victory_rates[best] - victory_rates[worst] < 1.0 {
+        if victory_rates[best] < 5.0 {
+            println!("You're pretty screwed either way.");
+        }
+        else {
+            println!("There's no significant difference either way.");
+        }
+    }
+    else if victory_rates.len() > 2 {
+        println!("{} would give you the best odds.", capitalize(&names[best]));
+    }
+    else if victory_rates[0] < victory_rates[1] {
+        println!("{} would give you a significant advantage.", capitalize(&names[1]));
+    }
+    else {
+        println!("{} would put you at a **disadvantage**.", capitalize(&names[1]));
+    }
 }
 
-fn thread_worker<F: FnMut() -> bool>(icount: usize, ucrew: u32, mcrew: u32,
-                                     player_attack_odds: &CaptureOdds,
-                                     player_defense_odds: &CaptureOdds,
-                                     mut should_continue: F)
-                                     -> (usize, usize, usize) {
-    let mut rng = rand::rngs::StdRng::from_entropy();
-    let mut ucount = 0;
-    let mut victories_by_auto = 0;
-    let mut victories_by_uni = 0;
-    while should_continue() {
-        ucount += 1;
-        for _ in 0 .. icount {
-            let (auto, uni) = attempt(&mut rng, ucrew, mcrew,
-                                      &player_attack_odds,
-                                      &player_defense_odds);
-            if auto { victories_by_auto += 1 }
-            if uni { victories_by_uni += 1}
+/// Describes which weapon each crew member on one side fights with, and
+/// prints it if the output is Markdown.
+fn allocation_report(invocation: &Invocation, who: &str, side: &Side) -> Value {
+    let allocation = side.allocation();
+    // crew with no weapon get null, which the Markdown table spells out
+    let weapon = |n: Option<usize>| match n {
+        None => Value::Null,
+        Some(n) => side.weapon_names[n].as_str().into(),
+    };
+    let mut table = Table::new(&[
+        ("role", "Role"),
+        ("first_crew", "From crew"),
+        ("last_crew", "To crew"),
+        ("weapon", "Weapon"),
+        ("strength", "Strength each"),
+    ]);
+    // one row for each run of crew that get the same weapon
+    for &(role, slots) in [("attack", &allocation.attack), ("defense", &allocation.defense)].iter() {
+        let mut first = 0;
+        for n in 1 ..= slots.len() {
+            if n < slots.len() && slots[n].0 == slots[first].0 { continue }
+            table.push(vec![
+                role.into(), (first as u32 + 1).into(), (n as u32).into(),
+                weapon(slots[first].0), slots[first].1.into(),
+            ]);
+            first = n;
         }
     }
-    (ucount, victories_by_auto, victories_by_uni)
+    let unused: Vec<(&str, u32)> = allocation.unused.iter().enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(n, &count)| (side.weapon_names[n].as_str(), count))
+        .collect();
+    let last = allocation.last_crew_strength();
+    if invocation.verbose && invocation.format == Format::Markdown {
+        println!("{}:\n", who);
+        let mut shown = table.clone();
+        for row in shown.rows.iter_mut() {
+            if let Value::Null = row[3] { row[3] = "(none)".into() }
+        }
+        print!("{}", shown.to_markdown());
+        println!();
+        if !unused.is_empty() {
+            let list: Vec<String> = unused.iter().map(|(name, count)| format!("{} x {}", name, count)).collect();
+            println!("Unused, since there isn't enough crew to carry them: {}  ", list.join(", "));
+        }
+        println!("The last crew member adds {:.1} attack and {:.1} defense.\n",
+                 last.attack_strength, last.defense_strength);
+    }
+    object(vec![
+        ("slots", table.to_value()),
+        ("unused", Value::Array(unused.iter().map(|&(name, count)| {
+            object(vec![("weapon", name.into()), ("count", count.into())])
+        }).collect())),
+        ("last_crew_attack", last.attack_strength.into()),
+        ("last_crew_defense", last.defense_strength.into()),
+    ])
 }
 
-fn proceed_with_invocation(invocation: &Invocation) {
-    let ustrengths = calculate_strengths(invocation.ucrew, invocation.ugov, &invocation.uwep);
-    let mstrengths = calculate_strengths(invocation.mcrew, invocation.mgov, &invocation.mwep);
-    let player_attack_odds = CaptureOdds::new(&ustrengths, &mstrengths);
-    let player_defense_odds = CaptureOdds::new(&mstrengths, &ustrengths);
-    if invocation.verbose {
+/// Prints the initial conditions, if the output is Markdown, and returns the
+/// first fields of the structured report.
+fn initial_conditions(invocation: &Invocation, boarding: &Boarding)
+                      -> Vec<(&'static str, Value)> {
+    let markdown = invocation.format == Format::Markdown;
+    let ustrength = boarding.ustrength;
+    let mstrength = boarding.mstrength;
+    let victory_odds = boarding.game_victory_odds();
+    let victory_casualties = boarding.game_victory_casualties();
+    let defeat_odds = boarding.game_defeat_odds();
+    let defeat_casualties = boarding.game_defeat_casualties();
+    let (victory_sd, victory_low, victory_high) = boarding.game_victory_casualty_spread();
+    let (defeat_sd, defeat_low, defeat_high) = boarding.game_defeat_casualty_spread();
+    if invocation.verbose && markdown {
         println!(r#"Initial Conditions
 ------------------
 
@@ -130,119 +193,858 @@ You  | {:>4} | {:>10.1} | {:>10.1}
 Them | {:>4} | {:>10.1} | {:>10.1}
 
 victory odds: {:.1}%  
-(casualties): {:.1}
+(casualties): {:.1} ± {:.1} (90% within {}–{})
 
 defeat odds:  {:.1}%  
-(casualties): {:.1}
+(casualties): {:.1} ± {:.1} (90% within {}–{})
+
+enemy rule:   {}
 "#,
-             invocation.ucrew, ustrengths.last().unwrap().attack_strength, ustrengths.last().unwrap().defense_strength,
-             invocation.mcrew, mstrengths.last().unwrap().attack_strength, mstrengths.last().unwrap().defense_strength,
-             player_attack_odds.capture_odds(invocation.ucrew, invocation.mcrew) * 100.0,
-             player_attack_odds.attacker_casualties(invocation.ucrew, invocation.mcrew),
-             player_defense_odds.capture_odds(invocation.mcrew, invocation.ucrew) * 100.0,
-             player_defense_odds.defender_casualties(invocation.mcrew, invocation.ucrew),
+             invocation.ucrew, ustrength.attack_strength, ustrength.defense_strength,
+             invocation.mcrew, mstrength.attack_strength, mstrength.defense_strength,
+             victory_odds * 100.0, victory_casualties, victory_sd, victory_low, victory_high,
+             defeat_odds * 100.0, defeat_casualties, defeat_sd, defeat_low, defeat_high,
+             boarding.enemy_rule.name(),
         );
+        println!("Weapon Allocation\n-----------------\n");
+    }
+    let scenario = invocation.scenario();
+    let allocation = object(vec![
+        ("you", allocation_report(invocation, "You", &scenario.you)),
+        ("them", allocation_report(invocation, "Them", &scenario.them)),
+    ]);
+    vec![
+        ("initial_conditions", object(vec![
+            ("you", object(vec![
+                ("crew", invocation.ucrew.into()),
+                ("attack", ustrength.attack_strength.into()),
+                ("defense", ustrength.defense_strength.into()),
+            ])),
+            ("them", object(vec![
+                ("crew", invocation.mcrew.into()),
+                ("attack", mstrength.attack_strength.into()),
+                ("defense", mstrength.defense_strength.into()),
+            ])),
+            ("enemy_rule", boarding.enemy_rule.name().into()),
+            ("allocation", allocation),
+        ])),
+        ("game_odds", object(vec![
+            ("victory", object(vec![
+                ("probability", victory_odds.into()),
+                ("casualties", victory_casualties.into()),
+                ("casualties_sd", victory_sd.into()),
+                ("casualties_p5", victory_low.into()),
+                ("casualties_p95", victory_high.into()),
+            ])),
+            ("defeat", object(vec![
+                ("probability", defeat_odds.into()),
+                ("casualties", defeat_casualties.into()),
+                ("casualties_sd", defeat_sd.into()),
+                ("casualties_p5", defeat_low.into()),
+                ("casualties_p95", defeat_high.into()),
+            ])),
+        ])),
+    ]
+}
+
+fn proceed_with_invocation(invocation: &Invocation) {
+    let markdown = invocation.format == Format::Markdown;
+    let boarding = invocation.scenario().prepare().unwrap();
+    // the fields of the structured report, in order
+    let mut report = initial_conditions(invocation, &boarding);
+    let strategies = &invocation.strategies;
+    let names: Vec<String> = strategies.iter().map(|x| x.name()).collect();
+    let exact_victory_rates: Vec<f64> = strategies.iter()
+        .map(|x| boarding.exact_victory_odds(x.as_ref()) * 100.0).collect();
+    if invocation.exact_only {
+        if markdown {
+            println!("Results\n-------\n");
+            for (n, (name, rate)) in names.iter().zip(exact_victory_rates.iter()).enumerate() {
+                let end = if n + 1 == names.len() { "" } else { "  " };
+                println!("Victory odds with {}: **{:.1}%**{}", name, rate, end);
+            }
+            println!();
+            print_advice(&names, &exact_victory_rates);
+        }
+        else {
+            report.push(("seed", Value::Null));
+            report.push(("confidence", Value::Null));
+            report.push(("trials", Value::Null));
+            report.push(("strategies", names.iter().zip(exact_victory_rates.iter())
+                         .map(|(name, &rate)| strategy_report(name, None, rate, None))
+                         .collect::<Vec<Value>>().into()));
+            report.push(("threads", Value::Array(Vec::new())));
+            print_report(invocation.format, &object(report));
+        }
+        return
     }
-    std::mem::drop(ustrengths);
-    std::mem::drop(mstrengths);
-    if let None = invocation.ucount.checked_mul(invocation.icount) {
+    if invocation.ucount.checked_mul(invocation.icount).is_none() {
         panic!("Absurdly huge total iteration count!");
     }
-    let (thread_work_counts, thread_victories_by_auto, thread_victories_by_uni)
-    = if invocation.tcount > 1 || invocation.force_threaded {
-        let icount = invocation.icount;
-        let ucrew = invocation.ucrew;
-        let mcrew = invocation.mcrew;
-        let player_attack_odds = Arc::new(player_attack_odds);
-        let player_defense_odds = Arc::new(player_defense_odds);
-        let remaining_work_units = Arc::new(DoomsdayClock::new(invocation.ucount));
-        let mut threads = Vec::with_capacity(invocation.tcount - 1);
-        for n in 1 .. invocation.tcount {
-            let player_attack_odds = player_attack_odds.clone();
-            let player_defense_odds = player_defense_odds.clone();
-            let remaining_work_units = remaining_work_units.clone();
-            threads.push(std::thread::Builder::new()
-                .name(format!("worker thread {}", n))
-                .spawn(move || {
-                    thread_worker(icount, ucrew, mcrew, player_attack_odds.as_ref(), player_defense_odds.as_ref(), || remaining_work_units.tick())
-                }).unwrap());
-        }
-        let mut work_counts = Vec::with_capacity(invocation.tcount);
-        let mut victories_by_auto = Vec::with_capacity(invocation.tcount);
-        let mut victories_by_uni = Vec::with_capacity(invocation.tcount);
-        let mut last = Instant::now();
-        let (a,b,c) = thread_worker(icount, ucrew, mcrew, player_attack_odds.as_ref(), player_defense_odds.as_ref(), || { let now = Instant::now(); if now - last >= Duration::new(1,0) { last = now; remaining_work_units.tick_loudly() } else { remaining_work_units.tick() }});
-        work_counts.push(a);
-        victories_by_auto.push(b);
-        victories_by_uni.push(c);
-        for thread in threads.into_iter() {
-            let (a,b,c) = match thread.join() {
-                Ok(x) => x,
-                Err(x) => {
-                    panic!("A thread panicked, so will we!\n{:?}", x);
-                },
-            };
-            work_counts.push(a);
-            victories_by_auto.push(b);
-            victories_by_uni.push(c);
-        }
-        (work_counts, victories_by_auto, victories_by_uni)
+    let seed = invocation.seed.unwrap_or_else(rand::random);
+    let plan = invocation.work_plan();
+    let z = confidence_z(invocation.confidence);
+    let threads = match invocation.precision {
+        None => run_work_units(&boarding, strategies, plan, seed, 0, invocation.ucount),
+        // keep going until every interval is narrow enough
+        Some(precision) => run_work_units_until(&boarding, strategies, plan, seed, |total| {
+            total.victories.iter().all(|&victories| {
+                let (lo, hi) = wilson_interval(victories, total.trials, z);
+                (hi - lo) * 50.0 <= precision
+            })
+        }),
+    };
+    let total = Tally::total(&threads);
+    let total_work_count = total.trials;
+    if !markdown {
+        report.push(("seed", seed.into()));
+        report.push(("confidence", invocation.confidence.into()));
+        report.push(("trials", total_work_count.into()));
+        report.push(("strategies", names.iter().zip(exact_victory_rates.iter())
+                     .enumerate().map(|(n, (name, &rate))| {
+                         strategy_report(name, Some((&total, n, z)), rate, invocation.prize_crew)
+                     }).collect::<Vec<Value>>().into()));
+        report.push(("threads", threads.iter().enumerate().map(|(n, thread)| object(vec![
+            ("thread", if n == 0 { "main".into() } else { n.to_string().into() }),
+            ("units", thread.units.into()),
+            ("victories", Value::Object(names.iter().zip(thread.victories.iter())
+                                        .map(|(name, &x)| (name.clone(), x.into()))
+                                        .collect())),
+        ])).collect::<Vec<Value>>().into()));
+        print_report(invocation.format, &object(report));
+        return
+    }
+    println!("Results\n-------\n");
+    println!("Number of trials: {:}  ", total_work_count);
+    println!("Random seed: {:}  ", seed);
+    for (n, name) in names.iter().enumerate() {
+        let victories = total.victories[n];
+        let (lo, hi) = wilson_interval(victories, total_work_count, z);
+        let end = if n + 1 == names.len() { "" } else { "  " };
+        println!("Victory rate with {}: **{:.1}%** ({}% CI {:.2}–{:.2}%; exact: {:.1}%){}",
+                 name, victories as f64 * 100.0 / total_work_count as f64,
+                 invocation.confidence * 100.0, lo * 100.0, hi * 100.0,
+                 exact_victory_rates[n], end);
+    }
+    println!();
+    if total.retreats.iter().any(|&x| x > 0) {
+        let mut table = Table::new(&[
+            ("strategy", "Strategy"),
+            ("victories", "Victory"),
+            ("defeats", "Defeat"),
+            ("retreats", "Retreat"),
+            ("mean_retreat_crew", "Crew after retreat"),
+        ]);
+        let rate = |x: usize| Value::Percent(x as f64 / total_work_count as f64);
+        for (n, name) in names.iter().enumerate() {
+            table.push(vec![
+                name.as_str().into(), rate(total.victories[n]),
+                rate(total.defeats[n]), rate(total.retreats[n]),
+                total.mean_retreat_crew(n).into(),
+            ]);
+        }
+        println!("{}", table.to_markdown());
+    }
+    print_advice(&names, &exact_victory_rates);
+    println!("\nSurviving Crew\n--------------\n");
+    println!("How much crew you have left after a victory:\n");
+    let prize_title = invocation.prize_crew.map(|x| format!("Win with {}+", x));
+    let mut columns = vec![
+        ("strategy", "Strategy"),
+        ("mean", "Mean"),
+        ("p5", "5%"),
+        ("p25", "25%"),
+        ("median", "Median"),
+        ("p75", "75%"),
+        ("p95", "95%"),
+    ];
+    if let Some(ref x) = prize_title { columns.push(("prize_crew_victory_rate", x)) }
+    let mut table = Table::new(&columns);
+    for (n, name) in names.iter().enumerate() {
+        let histogram = &total.ucrew_after_victory[n];
+        let mut row = vec![
+            name.as_str().into(), histogram.mean().into(),
+            histogram.quantile(0.05).into(), histogram.quantile(0.25).into(),
+            histogram.quantile(0.5).into(), histogram.quantile(0.75).into(),
+            histogram.quantile(0.95).into(),
+        ];
+        if let Some(k) = invocation.prize_crew {
+            row.push(Value::Percent(histogram.at_least(k) as f64 / total_work_count as f64));
+        }
+        table.push(row);
+    }
+    print!("{}", table.to_markdown());
+    println!("\nWork Statistics\n---------------\n");
+    let titles: Vec<String> = names.iter().map(|x| format!("{} wins", capitalize(x))).collect();
+    let mut columns = vec![("thread", "Thread"), ("units", "Units")];
+    columns.extend(names.iter().zip(titles.iter()).map(|(k, t)| (k.as_str(), t.as_str())));
+    let mut table = Table::new(&columns);
+    for (n, thread) in threads.iter().enumerate() {
+        let mut row = vec![if n == 0 { "main".into() } else { n.into() }, thread.units.into()];
+        row.extend(thread.victories.iter().map(|&x| Value::from(x)));
+        table.push(row);
+    }
+    print!("{}", table.to_markdown());
+}
+
+/// How many disagreements to list in Markdown output. The structured report
+/// has all of them.
+const MAX_DISAGREEMENTS_SHOWN: usize = 20;
+
+fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Attack => "attack",
+        Action::Defend => "defend",
+        Action::Retreat => "retreat",
+    }
+}
+
+fn proceed_with_optimal(invocation: &Invocation) {
+    let boarding = invocation.scenario().prepare().unwrap();
+    let mut report = initial_conditions(invocation, &boarding);
+    let policy = boarding.optimal_policy();
+    let optimal_victory_odds = policy.victory_odds(boarding.ucrew, boarding.mcrew);
+    let game_victory_odds = boarding.exact_victory_odds(&OneAtATime);
+    let mut disagreements = policy.disagreements(&boarding.player_attack_odds,
+                                                 &boarding.player_defense_odds);
+    let states = (boarding.ucrew.max(1) as usize - 1) * boarding.mcrew as usize;
+    let mut table = Table::new(&[
+        ("ucrew", "You"),
+        ("mcrew", "Them"),
+        ("game_action", "Game says"),
+        ("optimal_action", "Optimal"),
+        ("gain", "Gain"),
+    ]);
+    if invocation.format == Format::Markdown {
+        // the ones that matter most first
+        disagreements.sort_by(|a, b| b.gain.partial_cmp(&a.gain).unwrap());
+    }
+    for x in disagreements.iter() {
+        table.push(vec![
+            x.ucrew.into(), x.mcrew.into(),
+            action_name(x.game_action).into(), action_name(x.optimal_action).into(),
+            Value::Percent(x.gain),
+        ]);
+    }
+    if invocation.format != Format::Markdown {
+        report.push(("optimal_victory_odds", optimal_victory_odds.into()));
+        report.push(("one_at_a_time_victory_odds", game_victory_odds.into()));
+        report.push(("states", states.into()));
+        report.push(("disagreements", table.to_value()));
+        print_report(invocation.format, &object(report));
+        return
+    }
+    print!(r#"Optimal Policy
+--------------
+
+Victory odds playing optimally: **{:.1}%**  
+Victory odds with one-at-a-time: **{:.1}%**
+
+"#, optimal_victory_odds * 100.0, game_victory_odds * 100.0);
+    if disagreements.is_empty() {
+        println!("The game's odds are always right.");
+        return
+    }
+    println!("The optimal policy disagrees with the game's odds in {} of {} situations. Gain is how much better your odds are if you do the optimal thing there, instead of what the game suggests.\n",
+             disagreements.len(), states);
+    if table.rows.len() > MAX_DISAGREEMENTS_SHOWN {
+        let more = table.rows.len() - MAX_DISAGREEMENTS_SHOWN;
+        table.rows.truncate(MAX_DISAGREEMENTS_SHOWN);
+        print!("{}", table.to_markdown());
+        println!("\n...and {} more.", more);
+    }
+    else {
+        print!("{}", table.to_markdown());
+    }
+}
+
+fn proceed_with_equilibrium(invocation: &Invocation) {
+    let boarding = invocation.scenario().prepare().unwrap();
+    let mut report = initial_conditions(invocation, &boarding);
+    let (ucrew, mcrew) = (boarding.ucrew, boarding.mcrew);
+    let equilibrium = boarding.equilibrium();
+    let equilibrium_victory_odds = equilibrium.victory_odds(ucrew, mcrew);
+    let optimal_victory_odds = boarding.optimal_policy().victory_odds(ucrew, mcrew);
+    let game_victory_odds = boarding.exact_victory_odds(&OneAtATime);
+    let player_attack_probability = equilibrium.player_attack_probability(ucrew, mcrew);
+    let enemy_attack_probability = equilibrium.enemy_attack_probability(ucrew, mcrew);
+    let mixed_states = equilibrium.mixed_states();
+    let states = (ucrew.max(1) as usize - 1) * mcrew as usize;
+    if invocation.format != Format::Markdown {
+        report.push(("equilibrium_victory_odds", equilibrium_victory_odds.into()));
+        report.push(("optimal_victory_odds", optimal_victory_odds.into()));
+        report.push(("one_at_a_time_victory_odds", game_victory_odds.into()));
+        report.push(("player_attack_probability", player_attack_probability.into()));
+        report.push(("enemy_attack_probability", enemy_attack_probability.into()));
+        report.push(("states", states.into()));
+        report.push(("mixed_states", mixed_states.into()));
+        print_report(invocation.format, &object(report));
+        return
+    }
+    print!(r#"Equilibrium
+-----------
+
+Victory odds against a perfect enemy: **{:.1}%**  
+Victory odds against the game's AI, playing optimally: **{:.1}%**  
+Victory odds against the game's AI, with one-at-a-time: **{:.1}%**
+
+In the first round, you should attack {:.1}% of the time, and they should attack {:.1}% of the time. {} of {} situations call for mixing it up.
+
+"#, equilibrium_victory_odds * 100.0, optimal_victory_odds * 100.0,
+    game_victory_odds * 100.0, player_attack_probability * 100.0,
+    enemy_attack_probability * 100.0, mixed_states, states);
+    if optimal_victory_odds - equilibrium_victory_odds < 0.01 {
+        println!("The game's AI doesn't leave much on the table.");
+    }
+    else {
+        println!("The game's AI is giving you a **{:.1} point** head start.",
+                 (optimal_victory_odds - equilibrium_victory_odds) * 100.0);
+    }
+}
+
+/// The odds of losing your ship that `proceed_with_defense` aims to get
+/// under when it recommends loadouts.
+const SAFE_LOSS_ODDS: f64 = 0.01;
+
+/// Works out your odds of losing your ship, defending optimally, if you
+/// added this many of this weapon.
+fn defense_loss_odds(scenario: &Scenario, name: &str, weapon: Weapon, count: u32) -> f64 {
+    let mut scenario = scenario.clone();
+    scenario.you.add_weapon(name, weapon, count);
+    let boarding = scenario.prepare().unwrap();
+    boarding.optimal_defense().loss_odds(boarding.ucrew, boarding.mcrew)
+}
+
+fn proceed_with_defense(invocation: &Invocation) {
+    let scenario = invocation.scenario();
+    let boarding = scenario.prepare().unwrap();
+    let mut report = initial_conditions(invocation, &boarding);
+    let (ucrew, mcrew) = (boarding.ucrew, boarding.mcrew);
+    let mut table = Table::new(&[
+        ("strategy", "Strategy"),
+        ("loss_odds", "Ship lost"),
+        ("capture_odds", "Theirs captured"),
+        ("casualties", "Casualties"),
+    ]);
+    let mut solutions: Vec<(String, _)> = invocation.strategies.iter()
+        .map(|x| (x.name(), boarding.defense(x.as_ref()))).collect();
+    solutions.push(("optimal".to_owned(), boarding.optimal_defense()));
+    for (name, solution) in solutions.iter() {
+        table.push(vec![
+            name.as_str().into(),
+            Value::Percent(solution.loss_odds(ucrew, mcrew)),
+            Value::Percent(solution.capture_odds(ucrew, mcrew)),
+            solution.casualties(ucrew, mcrew).into(),
+        ]);
+    }
+    // what each defensive weapon could do for you, best first
+    let mut weapons: Vec<(&String, &Weapon)> = invocation.weapons.iter()
+        .filter(|(_, x)| x.defense_strength > 0.0).collect();
+    weapons.sort_by(|a, b| b.1.defense_strength.partial_cmp(&a.1.defense_strength).unwrap()
+                    .then(a.0.cmp(b.0)));
+    let mut loadouts = Table::new(&[
+        ("weapon", "Weapon"),
+        ("defense", "Defense"),
+        ("needed", "Needed"),
+        ("full_loss_odds", "Ship lost, one each"),
+    ]);
+    for (name, &weapon) in weapons.into_iter() {
+        let full_loss_odds = defense_loss_odds(&scenario, name, weapon, ucrew);
+        // the odds only get better with more weapons, so find the fewest
+        // that are enough
+        let needed = if full_loss_odds >= SAFE_LOSS_ODDS { None }
+        else {
+            let (mut lo, mut hi) = (0, ucrew);
+            while lo < hi {
+                let mid = (lo + hi) / 2;
+                if defense_loss_odds(&scenario, name, weapon, mid) < SAFE_LOSS_ODDS { hi = mid }
+                else { lo = mid + 1 }
+            }
+            Some(lo)
+        };
+        loadouts.push(vec![
+            name.as_str().into(), weapon.defense_strength.into(),
+            needed.into(), Value::Percent(full_loss_odds),
+        ]);
+    }
+    if invocation.format != Format::Markdown {
+        report.push(("strategies", table.to_value()));
+        report.push(("safe_loss_odds", SAFE_LOSS_ODDS.into()));
+        report.push(("loadouts", loadouts.to_value()));
+        print_report(invocation.format, &object(report));
+        return
+    }
+    println!("Defense\n-------\n");
+    println!("If they board you:\n");
+    print!("{}", table.to_markdown());
+    println!("\nDefensive Loadouts\n------------------\n");
+    println!("How many of each weapon you would need to add to get the odds of losing your ship under {}%, defending optimally, and the odds if you added one for every crew member:\n",
+             SAFE_LOSS_ODDS * 100.0);
+    print!("{}", loadouts.to_markdown());
+}
+
+fn proceed_with_solve(invocation: &Invocation) {
+    let axis = &invocation.axes[0];
+    let scenario = invocation.scenario();
+    let mut table = Table::new(&[
+        ("strategy", "Strategy"),
+        ("below", "Below"),
+        ("below_odds", "Odds"),
+        ("threshold", "Threshold"),
+        ("threshold_odds", "Odds"),
+        ("above", "Above"),
+        ("above_odds", "Odds"),
+        ("scanned", "Checked every value"),
+    ]);
+    let names: Vec<String> = invocation.strategies.iter().map(|x| x.name()).collect();
+    let thresholds = parallel_map(invocation.tcount, names.len(), |n| {
+        solve_threshold(&scenario, axis, invocation.strategies[n].as_ref(), invocation.target).unwrap()
+    });
+    for (name, threshold) in names.iter().zip(thresholds.iter()) {
+        table.push(vec![
+            name.as_str().into(),
+            threshold.below.map(|x| x.0).into(), threshold.below.map(|x| Value::Percent(x.1)).into(),
+            threshold.value.into(), threshold.odds.map(Value::Percent).into(),
+            threshold.above.map(|x| x.0).into(), threshold.above.map(|x| Value::Percent(x.1)).into(),
+            threshold.scanned.into(),
+        ]);
+    }
+    match invocation.format {
+        Format::Markdown => {
+            println!("Solve\n-----\n");
+            if helps_you(&axis.parameter) {
+                println!("The smallest {} that gives at least {}% victory odds:\n",
+                         axis.name, invocation.target * 100.0);
+            }
+            else {
+                println!("The largest {} that still gives at least {}% victory odds:\n",
+                         axis.name, invocation.target * 100.0);
+            }
+            print!("{}", table.to_markdown());
+            let missing = thresholds.iter().filter(|x| x.value.is_none()).count();
+            if missing > 0 {
+                println!("\nNothing from {} to {} is enough{}.",
+                         axis.values[0], axis.values[axis.values.len() - 1],
+                         if missing < thresholds.len() { " for some strategies" } else { "" });
+            }
+            if thresholds.iter().any(|x| x.scanned) {
+                println!("\nThe odds don't always get better (or worse) along the range, so where it says so, every value was checked, instead of just searching for the threshold.");
+            }
+        },
+        format => print_report(format, &object(vec![
+            ("axis", axis.name.as_str().into()),
+            ("target", invocation.target.into()),
+            ("strategies", table.to_value()),
+        ])),
+    }
+}
+
+/// Returns the `-uwep` options for a list of weapons.
+fn uwep_args<'a, I: Iterator<Item=(&'a str, u32)>>(weapons: I) -> String {
+    weapons.filter(|&(_, count)| count > 0)
+        .map(|(name, count)| format!("-uwep \"{} x {}\"", name, count))
+        .collect::<Vec<String>>().join(" ")
+}
+
+fn proceed_with_optimize(invocation: &Invocation) {
+    let budget = invocation.budget.unwrap();
+    let mut candidates: Vec<Candidate> = if invocation.costs.is_empty() {
+        invocation.weapons.iter().map(|(name, &weapon)| {
+            Candidate::weapon(name, weapon, 1.0)
+        }).collect()
     }
     else {
-        let mut it = 0..invocation.ucount;
-        let (a,b,c) = thread_worker(invocation.icount,
-                                    invocation.ucrew, invocation.mcrew,
-                                    &player_attack_odds, &player_defense_odds,
-                                    || it.next().is_some());
-        (vec![a], vec![b], vec![c])
+        invocation.costs.iter().map(|(name, cost)| {
+            Candidate::weapon(name, invocation.weapons[name], *cost)
+        }).collect()
     };
-    let total_work_count = thread_work_counts.iter().fold(0, |a,b| a+b) * invocation.icount;
-    let total_victories_by_auto = thread_victories_by_auto.iter().fold(0, |a,b| a+b);
-    let total_victories_by_uni = thread_victories_by_uni.iter().fold(0, |a,b| a+b);
-    let auto_victory_rate = total_victories_by_auto as f64 * 100.0 / total_work_count as f64;
-    let uni_victory_rate = total_victories_by_uni as f64 * 100.0 / total_work_count as f64;
-    print!(r#"Results
--------
-
-Number of trials: {:}  
-Victory rate with auto-go: **{:.1}%**  
-Victory rate with one-at-a-time: **{:.1}%**
-
-"#, total_work_count, auto_victory_rate, uni_victory_rate,
-    );
-    if (auto_victory_rate - uni_victory_rate).abs() < 1.0 {
-        if auto_victory_rate < 5.0 {
-            println!("You're pretty screwed either way.");
+    candidates.sort_by(|a, b| a.name.cmp(&b.name));
+    let scenario = invocation.scenario();
+    let strategy = invocation.strategies[0].as_ref();
+    let before = scenario.prepare().unwrap().exact_victory_odds(strategy);
+    let loadout = optimize_loadout(&scenario, &candidates, &[budget], strategy, invocation.tcount).unwrap();
+    let gains = marginal_gains(&scenario, &candidates, &loadout, strategy, invocation.tcount).unwrap();
+    // gains per unit of cost are tiny when costs are in credits, so the
+    // Markdown shows them per a round number near the dearest cost
+    let dearest = candidates.iter().map(|x| x.costs[0]).fold(0.0, f64::max);
+    let per = 10f64.powi(dearest.log10().floor().max(0.0) as i32);
+    let mut table = Table::new(&[
+        ("weapon", "Weapon"),
+        ("count", "Count"),
+        ("cost", "Cost each"),
+        ("marginal_gain", "Gain from one more"),
+        ("gain_per_cost", &format!("Points per {}", per)),
+    ]);
+    for ((candidate, &count), &gain) in candidates.iter().zip(loadout.counts.iter()).zip(gains.iter()) {
+        let per_cost = gain / candidate.costs[0];
+        table.push(vec![
+            candidate.name.as_str().into(), count.into(), candidate.costs[0].into(),
+            Value::Percent(gain),
+            match invocation.format {
+                Format::Markdown => per_cost * 100.0 * per,
+                _ => per_cost,
+            }.into(),
+        ]);
+    }
+    let args = uwep_args(candidates.iter().zip(loadout.counts.iter())
+                         .map(|(x, &count)| (x.name.as_str(), count)));
+    match invocation.format {
+        Format::Markdown => {
+            println!("Optimize\n--------\n");
+            println!("Spent: {} of {}  ", loadout.spent[0], budget);
+            println!("Victory odds with {}: **{:.1}%** (without the new weapons: {:.1}%)\n",
+                     strategy.name(), loadout.victory_odds * 100.0, before * 100.0);
+            print!("{}", table.to_markdown());
+            println!("\nGain from one more is how much one more of each would add to the victory odds. Points per {} is that in percentage points, for every {} it costs.", per, per);
+            if loadout.exhaustive {
+                println!("\nEvery loadout that fits was tried, so this is the best one.");
+            }
+            else {
+                println!("\nThere were too many loadouts to try them all, so this is the best a search found. There may be a better one.");
+            }
+            if !args.is_empty() {
+                println!("\nTo buy these, add:\n\n    {}", args);
+            }
+        },
+        format => {
+            print_report(format, &object(vec![
+                ("strategy", strategy.name().into()),
+                ("budget", budget.into()),
+                ("spent", loadout.spent[0].into()),
+                ("victory_odds_before", before.into()),
+                ("victory_odds", loadout.victory_odds.into()),
+                ("exhaustive", loadout.exhaustive.into()),
+                ("weapons", table.to_value()),
+            ]));
+        },
+    }
+}
+
+fn proceed_with_plan(invocation: &Invocation) {
+    let hull = invocation.hull.as_ref().unwrap();
+    let mut candidates = plan_candidates(invocation.data.as_ref().unwrap(), invocation.ucargo);
+    let strategy = invocation.strategies[0].as_ref();
+    let before = invocation.scenario().prepare().unwrap().exact_victory_odds(strategy);
+    // the outfits are picked with every bunk filled, or with the crew that
+    // was given, and then the crew is picked for them
+    let mut scenario = invocation.scenario();
+    match hull.crew {
+        Some(crew) => {
+            // more bunks are no use to a crew that won't grow
+            candidates.retain(|x| x.weapon.is_some());
+            for candidate in candidates.iter_mut() { candidate.bunks = 0 }
+            scenario.you.crew = crew;
+        },
+        None => scenario.you.crew = hull.bunks,
+    }
+    let mut loadout = optimize_loadout(&scenario, &candidates, &hull.space, strategy, invocation.tcount).unwrap();
+    let mut planned = with_loadout(&scenario, &candidates, &loadout.counts);
+    let bunks = planned.you.crew;
+    if hull.crew.is_none() {
+        // with auto-go, more crew can do worse, so this tries them all
+        let crews: Vec<u32> = (hull.required_crew ..= bunks).collect();
+        let odds = parallel_map(invocation.tcount, crews.len(), |n| {
+            let mut x = planned.clone();
+            x.you.crew = crews[n];
+            x.prepare().unwrap().exact_victory_odds(strategy)
+        });
+        // ties go to the smaller crew
+        let mut best = 0;
+        for n in 1 .. odds.len() {
+            if odds[n] > odds[best] + TIE { best = n }
         }
-        else {
-            println!("There's no significant difference either way.");
+        planned.you.crew = crews[best];
+        loadout.victory_odds = odds[best];
+        // and bunks nobody sleeps in don't need to be installed
+        let mut spare = bunks - planned.you.crew;
+        for (candidate, count) in candidates.iter().zip(loadout.counts.iter_mut()) {
+            if candidate.weapon.is_some() || candidate.bunks == 0 { continue }
+            let fewer = (spare / candidate.bunks).min(*count);
+            *count -= fewer;
+            spare -= fewer * candidate.bunks;
+        }
+        for (n, spent) in loadout.spent.iter_mut().enumerate() {
+            *spent = candidates.iter().zip(loadout.counts.iter())
+                .map(|(x, &count)| x.costs[n] * count as f64).sum();
         }
     }
-    else if auto_victory_rate < uni_victory_rate {
-        println!("One-at-a-time would give you a significant advantage.");
+    let crew = planned.you.crew;
+    // with the crew as planned, so more bunks don't add anything
+    let gains = parallel_map(invocation.tcount, candidates.len(), |n| {
+        match candidates[n].weapon {
+            Some(weapon) => {
+                let mut x = planned.clone();
+                x.you.add_weapon(&candidates[n].name, weapon, 1);
+                x.prepare().unwrap().exact_victory_odds(strategy) - loadout.victory_odds
+            },
+            None => 0.0,
+        }
+    });
+    let unused: u32 = planned.you.allocation().unused.iter().sum();
+    let mut table = Table::new(&[
+        ("outfit", "Outfit"),
+        ("in_cargo", "In cargo"),
+        ("count", "Count"),
+        ("outfit_space", "Outfit space"),
+        ("cargo_space", "Cargo space"),
+        ("bunks", "Bunks"),
+        ("marginal_gain", "Gain from one more"),
+    ]);
+    for ((candidate, &count), &gain) in candidates.iter().zip(loadout.counts.iter()).zip(gains.iter()) {
+        if count == 0 { continue }
+        table.push(vec![
+            candidate.name.as_str().into(), candidate.in_cargo.into(), count.into(),
+            (candidate.costs[0] * count as f64).into(),
+            (candidate.costs[1] * count as f64).into(),
+            (candidate.bunks * count).into(),
+            Value::Percent(gain),
+        ]);
     }
-    else {
-        println!("One-at-a-time would put you at a **disadvantage**.");
+    // -uwep replaces the ship's own weapons, so the list has to have them
+    // too, and a weapon both installed and in cargo is one entry
+    let mut weapons: Vec<(&str, u32)> = Vec::new();
+    for (name, &(_, count)) in planned.you.weapon_names.iter().zip(planned.you.weapons.iter()) {
+        let name = name.as_str();
+        match weapons.iter_mut().find(|x| x.0 == name) {
+            Some(x) => x.1 += count,
+            None => weapons.push((name, count)),
+        }
+    }
+    let args = format!("-ucrew {} {}", crew, uwep_args(weapons.into_iter()));
+    match invocation.format {
+        Format::Markdown => {
+            println!("Plan\n----\n");
+            println!("Ship: {}  ", hull.name);
+            println!("Free outfit space: {}, free cargo space: {}, bunks: {}  ", hull.space[0], hull.space[1], hull.bunks);
+            println!("Space used: {} outfit, {} cargo  ", loadout.spent[0], loadout.spent[1]);
+            match hull.crew {
+                Some(_) => println!("Crew: {} (as given)  ", crew),
+                None => println!("Crew: {} (whichever from {} to {} did best)  ", crew, hull.required_crew, bunks),
+            }
+            println!("Victory odds with {}: **{:.1}%** (as it is: {:.1}%)\n",
+                     strategy.name(), loadout.victory_odds * 100.0, before * 100.0);
+            if loadout.counts.iter().all(|&x| x == 0) {
+                println!("No outfits that fit would help with this crew.");
+            }
+            else {
+                print!("{}", table.to_markdown());
+                println!("\nGain from one more is how much one more of each would add to the victory odds with the same crew, if there were room for it.");
+            }
+            if !invocation.ucargo {
+                println!("\nThe game only fights with installed outfits, so no weapons were put in the cargo hold. Specify -ucargo to allow it anyway.");
+            }
+            if !loadout.exhaustive {
+                println!("\nThere were too many ways to fill the ship to try them all, so this is the best a search found. There may be a better one.");
+            }
+            if unused > 0 {
+                println!("\n{} of the weapons would go unused, since nobody would pick them for attack or for defense, so selling them would only make room.", unused);
+            }
+            println!("\nTo try it out, use:\n\n    {}", args);
+        },
+        format => {
+            print_report(format, &object(vec![
+                ("ship", hull.name.as_str().into()),
+                ("free_outfit_space", hull.space[0].into()),
+                ("free_cargo_space", hull.space[1].into()),
+                ("bunks", hull.bunks.into()),
+                ("outfit_space_used", loadout.spent[0].into()),
+                ("cargo_space_used", loadout.spent[1].into()),
+                ("crew", crew.into()),
+                ("crew_given", hull.crew.is_some().into()),
+                ("strategy", strategy.name().into()),
+                ("victory_odds_before", before.into()),
+                ("victory_odds", loadout.victory_odds.into()),
+                ("exhaustive", loadout.exhaustive.into()),
+                ("outfits", table.to_value()),
+                ("unused_weapons", unused.into()),
+                ("arguments", args.into()),
+            ]));
+        },
+    }
+}
+
+/// How many government names to list in a Markdown table before giving up
+/// and just saying how many more there are.
+const MAX_GOVERNMENTS_SHOWN: usize = 3;
+
+fn government_list(names: &[String], abbreviate: bool) -> String {
+    if abbreviate && names.len() > MAX_GOVERNMENTS_SHOWN + 1 {
+        format!("{} and {} more", names[.. MAX_GOVERNMENTS_SHOWN].join(", "),
+                names.len() - MAX_GOVERNMENTS_SHOWN)
+    }
+    else { names.join(", ") }
+}
+
+fn proceed_with_targets(invocation: &Invocation) {
+    let data = invocation.data.as_ref().unwrap();
+    let scenario = invocation.scenario();
+    let mut targets = evaluate_targets(&scenario.you, invocation.enemy_rule, data,
+                                       &invocation.strategies, invocation.tcount).unwrap();
+    // the sort is stable, so ties stay in order of ship name
+    match invocation.sort {
+        TargetOrder::Odds => targets.sort_by(|a, b| {
+            b.best_victory_odds().partial_cmp(&a.best_victory_odds()).unwrap()
+                .then(a.casualties.partial_cmp(&b.casualties).unwrap())
+        }),
+        TargetOrder::Casualties => targets.sort_by(|a, b| {
+            a.casualties.partial_cmp(&b.casualties).unwrap()
+        }),
+        TargetOrder::Crew => targets.sort_by_key(|x| x.crew),
+        TargetOrder::Ship => (),
+    }
+    let mut table = Table::new(&[
+        ("ship", "Ship"),
+        ("governments", "Governments"),
+        ("crew", "Crew"),
+        ("game_victory_odds", "Game's odds"),
+        ("victory_odds", "Victory odds"),
+        ("casualties", "Casualties"),
+        ("strategy", "Best strategy"),
+    ]);
+    let abbreviate = invocation.format == Format::Markdown;
+    for target in targets.iter() {
+        table.push(vec![
+            target.ship.as_str().into(),
+            government_list(&target.governments, abbreviate).into(),
+            target.crew.into(),
+            Value::Percent(target.game_victory_odds),
+            Value::Percent(target.best_victory_odds()),
+            target.casualties.into(),
+            invocation.strategies[target.best_strategy].name().into(),
+        ]);
+    }
+    match invocation.format {
+        Format::Markdown => {
+            println!("Targets\n-------\n");
+            println!("You: {} crew, {:.1} attack  ", invocation.ucrew,
+                     scenario.prepare().unwrap().ustrength.attack_strength);
+            println!("enemy rule: {}\n", invocation.enemy_rule.name());
+            print!("{}", table.to_markdown());
+            println!("\nVictory odds are exact, with whichever strategy does best against that target. Casualties are the crew you can expect to lose with that strategy, whether or not you win.");
+        },
+        Format::Csv => print!("{}", table.to_csv()),
+        Format::Json => {
+            print!("{}", to_json(&object(vec![
+                ("enemy_rule", invocation.enemy_rule.name().into()),
+                ("strategies", invocation.strategies.iter().map(|x| x.name()).collect::<Vec<String>>().into()),
+                ("targets", table.to_value()),
+            ])));
+        },
+    }
+}
+
+/// Returns the value of one coordinate of a sweep, for the report.
+fn coordinate(axis: &Axis, value: u32) -> Value {
+    if axis.parameter.is_numeric() { value.into() }
+    else { axis.parameter.label(value).into() }
+}
+
+/// Writes a two-dimensional sweep as a CSV grid, with the first axis down the
+/// side and the second across the top, for each strategy in turn.
+fn sweep_grid_csv(axes: &[Axis], names: &[String], points: &[SweepPoint]) -> String {
+    debug_assert_eq!(axes.len(), 2);
+    let mut ret = String::new();
+    for (n, name) in names.iter().enumerate() {
+        if n != 0 { ret.push('\n') }
+        ret.push_str(&csv_field(&format!("{}: {} \\ {}", name, axes[0].name, axes[1].name)));
+        for &value in axes[1].values.iter() {
+            ret.push_str(&format!(",{}", csv_field(&axes[1].parameter.label(value))));
+        }
+        ret.push('\n');
+        for (row, &value) in points.chunks(axes[1].values.len()).zip(axes[0].values.iter()) {
+            ret.push_str(&csv_field(&axes[0].parameter.label(value)));
+            for point in row.iter() {
+                ret.push_str(&format!(",{}", point.victory_odds[n]));
+            }
+            ret.push('\n');
+        }
     }
-    print!(r#"
-Work Statistics
----------------
+    ret
+}
 
-Thread | Units | Auto wins |  Uni wins
------- | ----- | --------- | ---------
-"#);
-    for n in 0 .. thread_work_counts.len() {
-        if n == 0 { print!("main  ") } else { print!("{:<6}", n) }
-        println!(" | {:>5} | {:>9} | {:>9}",
-                 thread_work_counts[n], thread_victories_by_auto[n],
-                 thread_victories_by_uni[n]);
+fn proceed_with_sweep(invocation: &Invocation) {
+    let exact = invocation.exact_only;
+    let seed = invocation.seed.unwrap_or_else(rand::random);
+    let names: Vec<String> = invocation.strategies.iter().map(|x| x.name()).collect();
+    let points = run_sweep(&invocation.scenario(), &invocation.axes,
+                           &invocation.strategies, invocation.work_plan(), seed,
+                           if exact { None } else { Some(invocation.ucount) }).unwrap();
+    let keys: Vec<String> = names.iter()
+        .map(|x| format!("{}_victory_odds", x.replace(&['-', ':', '.'][..], "_"))).collect();
+    let titles: Vec<String> = names.iter().map(|x| capitalize(x)).collect();
+    let mut columns: Vec<(&str, &str)> = invocation.axes.iter()
+        .map(|x| (x.name.as_str(), x.name.as_str())).collect();
+    columns.extend(keys.iter().zip(titles.iter()).map(|(k, t)| (k.as_str(), t.as_str())));
+    let mut table = Table::new(&columns);
+    for point in points.iter() {
+        let mut row: Vec<Value> = invocation.axes.iter().zip(point.coordinates.iter())
+            .map(|(axis, &x)| coordinate(axis, x)).collect();
+        row.extend(point.victory_odds.iter().map(|&x| Value::Percent(x)));
+        table.push(row);
+    }
+    let trials_per_point = if exact { None } else { Some(invocation.ucount * invocation.icount) };
+    match invocation.format {
+        Format::Markdown => {
+            println!("Sweep\n-----\n");
+            if exact {
+                println!("Exact victory odds at each point:\n");
+            }
+            else {
+                println!("Trials per point: {}  \nRandom seed: {}\n",
+                         invocation.ucount * invocation.icount, seed);
+            }
+            print!("{}", table.to_markdown());
+        },
+        Format::Csv if invocation.axes.len() == 2 => {
+            print!("{}", sweep_grid_csv(&invocation.axes, &names, &points));
+        },
+        Format::Csv => print!("{}", table.to_csv()),
+        Format::Json => {
+            print!("{}", to_json(&object(vec![
+                ("seed", if exact { Value::Null } else { seed.into() }),
+                ("trials_per_point", trials_per_point.into()),
+                ("axes", invocation.axes.iter().map(|x| object(vec![
+                    ("name", x.name.as_str().into()),
+                    ("values", x.values.iter().map(|&v| coordinate(x, v))
+                     .collect::<Vec<Value>>().into()),
+                ])).collect::<Vec<Value>>().into()),
+                ("points", table.to_value()),
+            ])));
+        },
     }
 }
 
 fn main() {
+    set_progress_hook(|count| eprint!("\r{}  \r", count));
     let invocation = match get_invocation() {
         Some(x) => x,
         None => std::process::exit(1),
     };
-    proceed_with_invocation(&invocation);
+    if invocation.mode == Mode::Solve {
+        proceed_with_solve(&invocation);
+    }
+    else if !invocation.axes.is_empty() {
+        proceed_with_sweep(&invocation);
+    }
+    else if invocation.mode == Mode::Optimal {
+        proceed_with_optimal(&invocation);
+    }
+    else if invocation.mode == Mode::Equilibrium {
+        proceed_with_equilibrium(&invocation);
+    }
+    else if invocation.mode == Mode::Defense {
+        proceed_with_defense(&invocation);
+    }
+    else if invocation.mode == Mode::Optimize {
+        proceed_with_optimize(&invocation);
+    }
+    else if invocation.mode == Mode::Plan {
+        proceed_with_plan(&invocation);
+    }
+    else if invocation.mode == Mode::Targets {
+        proceed_with_targets(&invocation);
+    }
+    else {
+        proceed_with_invocation(&invocation);
+    }
 }
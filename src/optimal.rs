@@ -0,0 +1,154 @@
+use crate::simulation::{round_powers, round_is_fought};
+use crate::capture_odds::CaptureOdds;
+use crate::state_table::StateTable;
+use crate::strategy::*;
+
+/// The best you can do against an enemy that follows a fixed rule, such as
+/// the game's AI, which attacks whenever the game gives it better than even
+/// odds of capturing you.
+///
+/// This is value iteration on the Markov decision process over (ucrew,
+/// mcrew), deciding one round at a time. Every round removes a crew member
+/// from one side, so if the states are visited from the smallest upward, a
+/// single sweep is enough for the values to converge.
+pub struct OptimalPolicy {
+    victory_odds: StateTable<f64>,
+    attack: StateTable<bool>,
+    enemy_rule: EnemyRule,
+}
+
+/// A state where the optimal policy disagrees with the game's advice.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Disagreement {
+    pub ucrew: u32,
+    pub mcrew: u32,
+    /// What the game's odds suggest.
+    pub game_action: Action,
+    pub optimal_action: Action,
+    /// How much better your odds of victory are if you do the optimal thing
+    /// here instead (and the optimal thing afterward either way).
+    pub gain: f64,
+}
+
+impl OptimalPolicy {
+    pub fn new(ucrew: u32, mcrew: u32,
+               player_attack_odds: &CaptureOdds,
+               player_defense_odds: &CaptureOdds,
+               enemy_rule: EnemyRule)
+               -> OptimalPolicy {
+        let mut ret = OptimalPolicy {
+            victory_odds: StateTable::new(ucrew, mcrew),
+            attack: StateTable::new(ucrew, mcrew),
+            enemy_rule,
+        };
+        for u in 0 ..= ucrew {
+            for m in 0 ..= mcrew {
+                let (odds, attack) = if m == 0 { (1.0, true) }
+                else if u <= 1 { (0.0, true) }
+                else {
+                    let situation = Situation { ucrew: u, mcrew: m, player_attack_odds, player_defense_odds, enemy_rule };
+                    let attack_odds = ret.action_odds(&situation, Action::Attack);
+                    let defend_odds = ret.action_odds(&situation, Action::Defend);
+                    // on a tie, do what the game would
+                    let attack = if attack_odds == defend_odds {
+                        situation.game_style_action() == Action::Attack
+                    } else { attack_odds > defend_odds };
+                    (attack_odds.max(defend_odds), attack)
+                };
+                ret.victory_odds.push(odds);
+                ret.attack.push(attack);
+            }
+        }
+        ret
+    }
+    /// Your odds of victory if you take the given action for one round and
+    /// play optimally afterward. Only valid once every smaller state has
+    /// been solved.
+    fn action_odds(&self, situation: &Situation, action: Action) -> f64 {
+        let (u, m) = (situation.ucrew, situation.mcrew);
+        let you_attack = action == Action::Attack;
+        let enemy_attacks = situation.enemy_attacks();
+        if !round_is_fought(action, enemy_attacks) { return 0.0 }
+        let (upow, tpow) = round_powers(u, m, you_attack, enemy_attacks,
+                                        situation.player_attack_odds,
+                                        situation.player_defense_odds);
+        let win = upow / tpow;
+        win * self.victory_odds(u, m - 1) + (1.0 - win) * self.victory_odds(u - 1, m)
+    }
+    pub fn victory_odds(&self, ucrew: u32, mcrew: u32) -> f64 {
+        self.victory_odds[(ucrew, mcrew)]
+    }
+    /// The optimal action, or `None` if the fight is already over.
+    pub fn action(&self, ucrew: u32, mcrew: u32) -> Option<Action> {
+        if ucrew <= 1 || mcrew == 0 { None }
+        else if self.attack[(ucrew, mcrew)] { Some(Action::Attack) }
+        else { Some(Action::Defend) }
+    }
+    /// Returns every state where the optimal action isn't what the game's
+    /// odds suggest, from the most crew down.
+    pub fn disagreements(&self, player_attack_odds: &CaptureOdds,
+                         player_defense_odds: &CaptureOdds)
+                         -> Vec<Disagreement> {
+        let mut ret = Vec::new();
+        for u in (2 ..= self.attack.ucrew()).rev() {
+            for m in (1 ..= self.attack.mcrew()).rev() {
+                let situation = Situation { ucrew: u, mcrew: m, player_attack_odds, player_defense_odds, enemy_rule: self.enemy_rule };
+                let game_action = situation.game_style_action();
+                let optimal_action = self.action(u, m).unwrap();
+                if game_action != optimal_action {
+                    ret.push(Disagreement {
+                        ucrew: u, mcrew: m, game_action, optimal_action,
+                        gain: self.victory_odds(u, m) - self.action_odds(&situation, game_action),
+                    });
+                }
+            }
+        }
+        ret
+    }
+}
+
+/// Follows the optimal policy, one round at a time.
+impl Strategy for OptimalPolicy {
+    fn name(&self) -> String { "optimal".to_owned() }
+    fn decide(&self, situation: &Situation) -> Decision {
+        let action = self.action(situation.ucrew, situation.mcrew)
+            .unwrap_or(Action::Attack);
+        Decision { action, rounds: 1 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn beats_every_strategy() {
+        let strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(AutoGo), Box::new(OneAtATime), Box::new(AlwaysAttack),
+            Box::new(AttackAbove(0.3)), Box::new(AutoGoAbove(6)),
+            Box::new(RetreatBelowCrew(Box::new(AutoGo), 4)),
+            Box::new(RetreatBelowOdds(Box::new(OneAtATime), 0.2)),
+        ];
+        let you = Side::new(12, Government::new(1.0, 2.0), vec![("Fragmentation Grenades".to_owned(), Weapon::new(1.3, 0.3), 4)]);
+        let them = Side::new(9, Government::new(1.4, 2.6), Vec::new());
+        for &enemy_rule in &[EnemyRule::default(), EnemyRule::AlwaysAttack, EnemyRule::AlwaysDefend, EnemyRule::Mirror] {
+            let boarding = Scenario { you: you.clone(), them: them.clone(), enemy_rule }.prepare().unwrap();
+            let optimal = boarding.optimal_policy();
+            for strategy in strategies.iter() {
+                let exact = boarding.exact_solution(strategy.as_ref());
+                // every state, not just the start, since each one is a small
+                // fight of its own
+                for u in 2 ..= boarding.ucrew {
+                    for m in 1 ..= boarding.mcrew {
+                        assert!(optimal.victory_odds(u, m) >= exact.victory_odds(u, m) - 1e-12,
+                                "{:?} {} at {}/{}: optimal {}, strategy {}", enemy_rule,
+                                strategy.name(), u, m, optimal.victory_odds(u, m), exact.victory_odds(u, m));
+                    }
+                }
+            }
+            // and following it gets what it promises
+            let exact = boarding.exact_solution(&optimal);
+            assert!((exact.victory_odds(12, 9) - optimal.victory_odds(12, 9)).abs() < 1e-12);
+        }
+    }
+}
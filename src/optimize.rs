@@ -0,0 +1,349 @@
+use crate::Weapon;
+use crate::simulation::{Scenario, parallel_map};
+use crate::strategy::Strategy;
+
+/// Something the optimizer may buy, and what one of them costs.
+#[derive(Clone,Debug)]
+pub struct Candidate {
+    pub name: String,
+    /// Its capture strengths, or `None` if it's only worth having for its
+    /// bunks.
+    pub weapon: Option<Weapon>,
+    /// What one takes out of each budget, in the same order as the budgets.
+    /// These can be in whatever units the budgets are: outfit space,
+    /// credits, or slots. None of them may be negative, and at least one
+    /// must be positive.
+    pub costs: Vec<f64>,
+    /// How many more crew one makes room for. The crew is assumed to fill
+    /// them.
+    pub bunks: u32,
+    /// Whether it's carried in the cargo hold instead of installed.
+    pub in_cargo: bool,
+}
+
+impl Candidate {
+    /// A weapon with a single cost.
+    pub fn weapon(name: &str, weapon: Weapon, cost: f64) -> Candidate {
+        Candidate { name: name.to_owned(), weapon: Some(weapon), costs: vec![cost], bunks: 0, in_cargo: false }
+    }
+}
+
+/// How many of each candidate to buy, and what that gets you.
+#[derive(Clone,Debug)]
+pub struct Loadout {
+    /// One count per candidate, in the same order.
+    pub counts: Vec<u32>,
+    /// How much of each budget was spent.
+    pub spent: Vec<f64>,
+    pub victory_odds: f64,
+    /// Whether every loadout that fits was tried, so that this one is
+    /// certainly the best. If not, it's the best a local search found.
+    pub exhaustive: bool,
+}
+
+/// If there are more loadouts than this that fit the budgets,
+/// `optimize_loadout` searches instead of trying them all.
+pub const MAX_EXHAUSTIVE_LOADOUTS: usize = 20000;
+
+// leeway for costs that don't add up exactly, like 0.1 + 0.2
+const EPSILON: f64 = 1e-9;
+
+/// How close two victory odds have to be to count as a tie, so that
+/// rounding error doesn't pick a winner.
+pub const TIE: f64 = 1e-12;
+
+/// Returns the scenario with the loadout added to your side.
+pub fn with_loadout(base: &Scenario, candidates: &[Candidate], counts: &[u32]) -> Scenario {
+    let mut ret = base.clone();
+    for (candidate, &count) in candidates.iter().zip(counts.iter()) {
+        if count == 0 { continue }
+        ret.you.crew += candidate.bunks * count;
+        if let Some(weapon) = candidate.weapon { ret.you.add_weapon(&candidate.name, weapon, count) }
+    }
+    ret
+}
+
+// a loadout only ever adds crew, so once the base has been checked, this
+// can't fail
+fn victory_odds(base: &Scenario, candidates: &[Candidate], counts: &[u32],
+                strategy: &dyn Strategy) -> f64 {
+    with_loadout(base, candidates, counts).prepare().unwrap().exact_victory_odds(strategy)
+}
+
+fn spending(candidates: &[Candidate], counts: &[u32], budgets: &[f64]) -> Vec<f64> {
+    let mut ret = vec![0.0; budgets.len()];
+    for (candidate, &count) in candidates.iter().zip(counts.iter()) {
+        for (spent, &cost) in ret.iter_mut().zip(candidate.costs.iter()) {
+            *spent += cost * count as f64;
+        }
+    }
+    ret
+}
+
+fn fits(spent: &[f64], budgets: &[f64]) -> bool {
+    spent.iter().zip(budgets.iter()).all(|(&spent, &budget)| spent <= budget + EPSILON)
+}
+
+// how much of the budgets a loadout uses up, all told
+fn share(spent: &[f64], budgets: &[f64]) -> f64 {
+    spent.iter().zip(budgets.iter())
+        .map(|(&spent, &budget)| if spent > 0.0 { spent / budget } else { 0.0 })
+        .sum()
+}
+
+/// Returns the most of each candidate that could be worth buying: as many as
+/// the budgets allow, but no more weapons than there could be crew to use
+/// them, since calculate_strengths only hands out one per crew member.
+fn most_worth_buying(base: &Scenario, candidates: &[Candidate], budgets: &[f64]) -> Vec<u32> {
+    let affordable: Vec<u32> = candidates.iter().map(|candidate| {
+        candidate.costs.iter().zip(budgets.iter())
+            .filter(|&(&cost, _)| cost > 0.0)
+            .map(|(&cost, &budget)| ((budget + EPSILON) / cost).floor())
+            .fold(f64::INFINITY, f64::min)
+            .min(u32::MAX as f64) as u32
+    }).collect();
+    let crew = candidates.iter().zip(affordable.iter())
+        .fold(base.you.crew, |crew, (candidate, &count)| {
+            crew.saturating_add(candidate.bunks.saturating_mul(count))
+        });
+    candidates.iter().zip(affordable.iter()).map(|(candidate, &count)| {
+        if candidate.weapon.is_some() { count.min(crew) } else { count }
+    }).collect()
+}
+
+/// Returns every loadout within `most` that fits the budgets, or `None` if
+/// there are more than `limit` of them.
+fn every_loadout(candidates: &[Candidate], budgets: &[f64], most: &[u32],
+                 limit: usize) -> Option<Vec<Vec<u32>>> {
+    fn add(candidates: &[Candidate], budgets: &[f64], most: &[u32], limit: usize,
+           counts: &mut Vec<u32>, spent: &mut Vec<f64>, ret: &mut Vec<Vec<u32>>) -> bool {
+        let n = counts.len();
+        if n == candidates.len() {
+            ret.push(counts.clone());
+            return ret.len() <= limit
+        }
+        for count in 0 ..= most[n] {
+            let now: Vec<f64> = spent.iter().zip(candidates[n].costs.iter())
+                .map(|(&spent, &cost)| spent + cost * count as f64).collect();
+            if !fits(&now, budgets) { break }
+            counts.push(count);
+            let before = std::mem::replace(spent, now);
+            let more = add(candidates, budgets, most, limit, counts, spent, ret);
+            *spent = before;
+            counts.pop();
+            if !more { return false }
+        }
+        true
+    }
+    let mut ret = Vec::new();
+    if add(candidates, budgets, most, limit, &mut Vec::new(), &mut vec![0.0; budgets.len()], &mut ret) {
+        Some(ret)
+    }
+    else { None }
+}
+
+/// Spends the budgets on whatever mix of candidates gives the best exact
+/// victory odds with `strategy`. Ties go to whichever spends the least.
+///
+/// Weapons past the crew size never get used, so there are only so many
+/// loadouts worth trying. If there are no more than
+/// `MAX_EXHAUSTIVE_LOADOUTS` of them, this tries every one. Otherwise it
+/// falls back on a local search, which can miss the best loadout; the
+/// result's `exhaustive` says which happened.
+pub fn optimize_loadout(base: &Scenario, candidates: &[Candidate], budgets: &[f64],
+                        strategy: &dyn Strategy, tcount: usize) -> Result<Loadout, String> {
+    base.prepare()?;
+    let most = most_worth_buying(base, candidates, budgets);
+    match every_loadout(candidates, budgets, &most, MAX_EXHAUSTIVE_LOADOUTS) {
+        Some(loadouts) => Ok(try_every_loadout(base, candidates, budgets, strategy, tcount, loadouts)),
+        None => Ok(search_loadouts(base, candidates, budgets, &most, strategy, tcount)),
+    }
+}
+
+fn try_every_loadout(base: &Scenario, candidates: &[Candidate], budgets: &[f64],
+                     strategy: &dyn Strategy, tcount: usize,
+                     loadouts: Vec<Vec<u32>>) -> Loadout {
+    let odds = parallel_map(tcount, loadouts.len(), |n| {
+        victory_odds(base, candidates, &loadouts[n], strategy)
+    });
+    let mut best: Option<Loadout> = None;
+    for (counts, victory_odds) in loadouts.into_iter().zip(odds) {
+        let spent = spending(candidates, &counts, budgets);
+        let better = match best {
+            None => true,
+            // rounding error doesn't count
+            Some(ref best) => victory_odds > best.victory_odds + TIE
+                || (victory_odds >= best.victory_odds - TIE
+                    && share(&spent, budgets) < share(&best.spent, budgets)),
+        };
+        if better { best = Some(Loadout { counts, spent, victory_odds, exhaustive: true }) }
+    }
+    // the empty loadout always fits, so there's at least one
+    best.unwrap()
+}
+
+// Searches for a good loadout when there are too many to try. It buys
+// greedily, gives back whatever doesn't help, then tries trading one of
+// something for as many as fit of something else, and goes around again
+// for as long as a trade helps.
+fn search_loadouts(base: &Scenario, candidates: &[Candidate], budgets: &[f64], most: &[u32],
+                   strategy: &dyn Strategy, tcount: usize) -> Loadout {
+    let counts = vec![0; candidates.len()];
+    let mut ret = Loadout {
+        victory_odds: victory_odds(base, candidates, &counts, strategy),
+        spent: spending(candidates, &counts, budgets),
+        counts,
+        exhaustive: false,
+    };
+    loop {
+        buy_greedily(base, candidates, budgets, most, strategy, tcount, &mut ret);
+        give_back(base, candidates, budgets, strategy, &mut ret);
+        if !trade(base, candidates, budgets, most, strategy, tcount, &mut ret) { break }
+    }
+    ret
+}
+
+// Keeps buying whichever candidate gives the most odds for the share of the
+// budgets it uses, a batch at a time, halving the batch size whenever
+// nothing in a full batch helps or fits.
+fn buy_greedily(base: &Scenario, candidates: &[Candidate], budgets: &[f64], most: &[u32],
+                strategy: &dyn Strategy, tcount: usize, ret: &mut Loadout) {
+    let shares: Vec<f64> = candidates.iter()
+        .map(|candidate| share(&candidate.costs, budgets)).collect();
+    // start with batches of about an eighth of what the budgets could buy
+    let largest = most.iter().cloned().max().unwrap_or(0);
+    let mut batch = 1;
+    while batch * 2 <= largest / 8 { batch *= 2 }
+    loop {
+        let gains = parallel_map(tcount, candidates.len(), |n| {
+            if ret.counts[n] + batch > most[n] { return None }
+            let mut counts = ret.counts.clone();
+            counts[n] += batch;
+            if !fits(&spending(candidates, &counts, budgets), budgets) { return None }
+            let odds = victory_odds(base, candidates, &counts, strategy);
+            Some(((odds - ret.victory_odds) / (shares[n] * batch as f64), odds))
+        });
+        let best = gains.iter().enumerate()
+            .filter_map(|(n, x)| x.map(|x| (n, x)))
+            .filter(|(_, (value, _))| *value > 0.0)
+            .max_by(|a, b| (a.1).0.partial_cmp(&(b.1).0).unwrap());
+        match best {
+            Some((n, (_, odds))) => {
+                ret.counts[n] += batch;
+                ret.spent = spending(candidates, &ret.counts, budgets);
+                ret.victory_odds = odds;
+            },
+            None if batch > 1 => batch /= 2,
+            None => break,
+        }
+    }
+}
+
+// A whole batch may have been bought when only part of it helped, so this
+// gives back whatever doesn't change the odds.
+fn give_back(base: &Scenario, candidates: &[Candidate], budgets: &[f64],
+             strategy: &dyn Strategy, ret: &mut Loadout) {
+    for n in 0 .. candidates.len() {
+        while ret.counts[n] > 0 {
+            let mut counts = ret.counts.clone();
+            counts[n] -= 1;
+            if victory_odds(base, candidates, &counts, strategy) < ret.victory_odds { break }
+            ret.counts = counts;
+        }
+    }
+    ret.spent = spending(candidates, &ret.counts, budgets);
+}
+
+// Tries giving back one of each candidate for as many as fit of each other
+// one, and makes whichever trade helps the most. Returns whether there was
+// one that helped.
+fn trade(base: &Scenario, candidates: &[Candidate], budgets: &[f64], most: &[u32],
+         strategy: &dyn Strategy, tcount: usize, ret: &mut Loadout) -> bool {
+    let count = candidates.len();
+    let trades = parallel_map(tcount, count * count, |n| {
+        let (from, to) = (n / count, n % count);
+        if from == to || ret.counts[from] == 0 || ret.counts[to] >= most[to] { return None }
+        let mut counts = ret.counts.clone();
+        counts[from] -= 1;
+        let mut added = false;
+        while counts[to] < most[to] {
+            counts[to] += 1;
+            if !fits(&spending(candidates, &counts, budgets), budgets) {
+                counts[to] -= 1;
+                break
+            }
+            added = true;
+        }
+        if !added { return None }
+        Some((victory_odds(base, candidates, &counts, strategy), counts))
+    });
+    let best = trades.into_iter().flatten()
+        .filter(|(odds, _)| *odds > ret.victory_odds)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    match best {
+        Some((odds, counts)) => {
+            ret.spent = spending(candidates, &counts, budgets);
+            ret.counts = counts;
+            ret.victory_odds = odds;
+            true
+        },
+        None => false,
+    }
+}
+
+/// Returns how much one more of each candidate would add to the victory
+/// odds, whether or not the budgets have room for it.
+pub fn marginal_gains(base: &Scenario, candidates: &[Candidate], loadout: &Loadout,
+                      strategy: &dyn Strategy, tcount: usize) -> Result<Vec<f64>, String> {
+    base.prepare()?;
+    Ok(parallel_map(tcount, candidates.len(), |n| {
+        let mut counts = loadout.counts.clone();
+        counts[n] += 1;
+        victory_odds(base, candidates, &counts, strategy) - loadout.victory_odds
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // 2 crew against 3, with a Laser Rifle costing 1 and Nerve Gas 6 out of
+    // a budget of 12. Buying by odds per cost gets stuck at one of each, but
+    // two Nerve Gas do much better.
+    fn stuck() -> (Scenario, Vec<Candidate>) {
+        let base = Scenario::new(Side::new(2, Government::new(1.0, 2.0), Vec::new()),
+                                 Side::new(3, Government::new(1.0, 2.0), Vec::new()));
+        let candidates = vec![
+            Candidate::weapon("Laser Rifle", Weapon::new(0.6, 0.8), 1.0),
+            Candidate::weapon("Nerve Gas", Weapon::new(2.8, 0.8), 6.0),
+        ];
+        (base, candidates)
+    }
+
+    #[test]
+    fn finds_the_best() {
+        let (base, candidates) = stuck();
+        let loadout = optimize_loadout(&base, &candidates, &[12.0], &OneAtATime, 2).unwrap();
+        assert!(loadout.exhaustive);
+        assert_eq!(loadout.counts, vec![0, 2]);
+        assert_eq!(loadout.spent, vec![12.0]);
+        assert!((loadout.victory_odds - 0.290).abs() < 0.001);
+        // nothing else that fits does any better
+        for lasers in 0 ..= 12 {
+            for gas in 0 ..= (12 - lasers) / 6 {
+                assert!(victory_odds(&base, &candidates, &[lasers, gas], &OneAtATime) <= loadout.victory_odds);
+            }
+        }
+    }
+
+    #[test]
+    fn search_gets_unstuck() {
+        let (base, candidates) = stuck();
+        let most = most_worth_buying(&base, &candidates, &[12.0]);
+        assert_eq!(most, vec![2, 2]);
+        let loadout = search_loadouts(&base, &candidates, &[12.0], &most, &OneAtATime, 2);
+        assert!(!loadout.exhaustive);
+        assert_eq!(loadout.counts, vec![0, 2]);
+    }
+}
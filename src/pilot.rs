@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use crate::data_file::*;
+use crate::game_data::load_outfit_list;
+
+/// A ship in the player's fleet, as recorded in a pilot save file.
+#[derive(Clone,Debug)]
+pub struct SavedShip {
+    pub model: String,
+    pub name: String,
+    pub crew: u32,
+    pub parked: bool,
+    /// Installed outfits, and how many of each.
+    pub outfits: Vec<(String, u32)>,
+    /// Outfits sitting in the cargo hold, and how many of each.
+    pub cargo_outfits: Vec<(String, u32)>,
+}
+
+impl SavedShip {
+    fn load(node: &DataNode) -> SavedShip {
+        let mut ret = SavedShip {
+            model: node.token(1).to_owned(),
+            name: String::new(),
+            crew: 0,
+            parked: false,
+            outfits: Vec::new(),
+            cargo_outfits: Vec::new(),
+        };
+        for child in node.children.iter() {
+            match child.token(0) {
+                "name" => ret.name = child.token(1).to_owned(),
+                "crew" => ret.crew = child.value(1).unwrap_or(0.0).max(0.0) as u32,
+                "parked" => ret.parked = true,
+                "outfits" => ret.outfits = load_outfit_list(child),
+                "cargo" => {
+                    for grandchild in child.children.iter() {
+                        if grandchild.token(0) == "outfits" {
+                            ret.cargo_outfits = load_outfit_list(grandchild);
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+        ret
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct Pilot {
+    pub ships: Vec<SavedShip>,
+    flagship_index: Option<usize>,
+}
+
+impl Pilot {
+    pub fn load(path: &Path) -> Result<Pilot, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(x) => return Err(format!("Unable to read {}: {}", path.display(), x)),
+        };
+        let mut ships = Vec::new();
+        let mut flagship_index = None;
+        for node in parse(&text).iter() {
+            match node.token(0) {
+                "ship" => ships.push(SavedShip::load(node)),
+                "flagship index" => flagship_index = node.value(1).map(|x| x as usize),
+                _ => (),
+            }
+        }
+        Ok(Pilot { ships, flagship_index })
+    }
+    /// Returns the player's flagship. If the save doesn't say which ship
+    /// that is, it's the first one that isn't parked, the same way the game
+    /// picks one.
+    pub fn flagship(&self) -> Option<&SavedShip> {
+        match self.flagship_index.and_then(|x| self.ships.get(x)) {
+            Some(x) => Some(x),
+            None => self.ships.iter().find(|x| !x.parked),
+        }
+    }
+    pub fn ship_named(&self, name: &str) -> Option<&SavedShip> {
+        self.ships.iter().find(|x| x.name == name)
+    }
+}
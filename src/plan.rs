@@ -0,0 +1,70 @@
+use crate::game_data::{GameData, Ship};
+use crate::optimize::Candidate;
+
+/// The attributes that installing outfits uses up, in the order the
+/// planner's budgets and costs are in.
+pub const SPACE_ATTRIBUTES: [&str; 2] = ["outfit space", "cargo space"];
+
+/// How much room a ship has left for more outfits and crew.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Hull {
+    pub name: String,
+    /// Free outfit space, then free cargo space, as in `SPACE_ATTRIBUTES`.
+    pub space: Vec<f64>,
+    /// How many crew the ship can carry.
+    pub bunks: u32,
+    /// How few crew the ship can fly with. This is at least 1, and no more
+    /// than `bunks`.
+    pub required_crew: u32,
+    /// The crew to plan for, if it was given. Otherwise the plan picks
+    /// whatever crew from `required_crew` up to every bunk filled does best.
+    pub crew: Option<u32>,
+}
+
+impl Hull {
+    /// The room left on a ship with the outfits it has installed. A ship that
+    /// is somehow over its limits has no room left, rather than less than
+    /// none.
+    pub fn new(ship: &Ship, data: &GameData) -> Hull {
+        let bunks = ship.get("bunks", data).max(0.0) as u32;
+        Hull {
+            name: ship.name.clone(),
+            space: SPACE_ATTRIBUTES.iter().map(|x| ship.get(x, data).max(0.0)).collect(),
+            bunks,
+            required_crew: (ship.get("required crew", data).max(1.0) as u32).min(bunks),
+            crew: None,
+        }
+    }
+}
+
+/// Returns every outfit that would help in a boarding action: capture
+/// weapons, and outfits that add bunks. Outfits that make more room than
+/// they take up, such as cargo expansions, are left out, as are outfits that
+/// take up no room at all.
+///
+/// The game only fights with installed outfits, but with `in_cargo`, each
+/// capture weapon is also offered carried in the cargo hold, where it takes
+/// up as much cargo space as its mass, as if it counted anyway (like
+/// `-ucargo` does for a saved ship).
+pub fn plan_candidates(data: &GameData, in_cargo: bool) -> Vec<Candidate> {
+    let mut ret: Vec<Candidate> = data.outfits.values().filter_map(|outfit| {
+        let weapon = outfit.as_weapon();
+        let bunks = outfit.get("bunks").max(0.0) as u32;
+        if weapon.is_none() && bunks == 0 { return None }
+        let costs: Vec<f64> = SPACE_ATTRIBUTES.iter().map(|x| 0.0 - outfit.get(x)).collect();
+        if costs.iter().any(|&x| x < 0.0) || costs.iter().all(|&x| x == 0.0) { return None }
+        Some(Candidate { name: outfit.name.clone(), weapon, costs, bunks, in_cargo: false })
+    }).collect();
+    if in_cargo {
+        // an outfit in the cargo hold is just cargo, so its bunks and space
+        // attributes don't do anything
+        ret.extend(data.outfits.values().filter_map(|outfit| {
+            let weapon = outfit.as_weapon();
+            let mass = outfit.get("mass");
+            if weapon.is_none() || mass <= 0.0 { return None }
+            Some(Candidate { name: outfit.name.clone(), weapon, costs: vec![0.0, mass], bunks: 0, in_cargo: true })
+        }));
+    }
+    ret.sort_by(|a, b| (&a.name, a.in_cargo).cmp(&(&b.name, b.in_cargo)));
+    ret
+}
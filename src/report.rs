@@ -0,0 +1,242 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Format {
+    Markdown,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A report, in a form that can be written out as JSON or CSV.
+#[derive(Clone,Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// A fraction that should be shown to humans as a percentage.
+    Percent(f64),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl From<bool> for Value { fn from(x: bool) -> Value { Value::Bool(x) } }
+impl From<u32> for Value { fn from(x: u32) -> Value { Value::Int(x as i64) } }
+impl From<u64> for Value { fn from(x: u64) -> Value { Value::Int(x as i64) } }
+impl From<usize> for Value { fn from(x: usize) -> Value { Value::Int(x as i64) } }
+impl From<f64> for Value { fn from(x: f64) -> Value { Value::Float(x) } }
+impl From<&str> for Value { fn from(x: &str) -> Value { Value::Str(x.to_owned()) } }
+impl From<String> for Value { fn from(x: String) -> Value { Value::Str(x) } }
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(x: Option<T>) -> Value {
+        match x { Some(x) => x.into(), None => Value::Null }
+    }
+}
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(x: Vec<T>) -> Value {
+        Value::Array(x.into_iter().map(|x| x.into()).collect())
+    }
+}
+
+/// Makes a `Value::Object` out of a list of `(key, value)` pairs.
+pub fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_scalar(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(x) => write!(out, "{}", x).unwrap(),
+        Value::Int(x) => write!(out, "{}", x).unwrap(),
+        Value::Float(x) | Value::Percent(x) if x.is_finite() => write!(out, "{}", x).unwrap(),
+        Value::Float(_) | Value::Percent(_) => out.push_str("null"),
+        _ => unreachable!(),
+    }
+}
+
+fn write_json(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Str(x) => write_json_string(out, x),
+        Value::Array(x) if x.is_empty() => out.push_str("[]"),
+        Value::Object(x) if x.is_empty() => out.push_str("{}"),
+        Value::Array(x) => {
+            out.push('[');
+            for (n, el) in x.iter().enumerate() {
+                if n != 0 { out.push(',') }
+                write!(out, "\n{:1$}", "", indent + 2).unwrap();
+                write_json(out, el, indent + 2);
+            }
+            write!(out, "\n{:1$}]", "", indent).unwrap();
+        },
+        Value::Object(x) => {
+            out.push('{');
+            for (n, (k, v)) in x.iter().enumerate() {
+                if n != 0 { out.push(',') }
+                write!(out, "\n{:1$}", "", indent + 2).unwrap();
+                write_json_string(out, k);
+                out.push_str(": ");
+                write_json(out, v, indent + 2);
+            }
+            write!(out, "\n{:1$}}}", "", indent).unwrap();
+        },
+        _ => write_scalar(out, value),
+    }
+}
+
+pub fn to_json(value: &Value) -> String {
+    let mut ret = String::new();
+    write_json(&mut ret, value, 0);
+    ret.push('\n');
+    ret
+}
+
+/// Quotes a CSV field, if it needs it.
+pub fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+    else { s.to_owned() }
+}
+
+fn write_csv(out: &mut String, path: &str, value: &Value) {
+    let join = |k: &str| if path.is_empty() { k.to_owned() } else { format!("{}.{}", path, k) };
+    match value {
+        Value::Array(x) => {
+            for (n, el) in x.iter().enumerate() { write_csv(out, &join(&n.to_string()), el) }
+        },
+        Value::Object(x) => {
+            for (k, v) in x.iter() { write_csv(out, &join(k), v) }
+        },
+        Value::Str(x) => writeln!(out, "{},{}", csv_field(path), csv_field(x)).unwrap(),
+        Value::Null => writeln!(out, "{},", csv_field(path)).unwrap(),
+        _ => {
+            out.push_str(&csv_field(path));
+            out.push(',');
+            write_scalar(out, value);
+            out.push('\n');
+        },
+    }
+}
+
+/// Flattens a report into `field,value` rows, one per scalar. A field's name
+/// is its path through the report, e.g. `strategies.0.victories`.
+pub fn to_csv(value: &Value) -> String {
+    let mut ret = String::from("field,value\n");
+    write_csv(&mut ret, "", value);
+    ret
+}
+
+/// A table of results, with one column per field.
+#[derive(Clone,Debug)]
+pub struct Table {
+    /// The key for each column, used in JSON and CSV output, and its title,
+    /// used in Markdown output.
+    pub columns: Vec<(String, String)>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+fn markdown_cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_owned(),
+        Value::Float(x) => format!("{:.1}", x),
+        Value::Percent(x) => format!("{:.1}%", x * 100.0),
+        Value::Str(x) => x.clone(),
+        _ => {
+            let mut ret = String::new();
+            write_scalar(&mut ret, value);
+            ret
+        },
+    }
+}
+
+impl Table {
+    pub fn new(columns: &[(&str, &str)]) -> Table {
+        Table {
+            columns: columns.iter().map(|(k, t)| (k.to_string(), t.to_string())).collect(),
+            rows: Vec::new(),
+        }
+    }
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+    pub fn to_markdown(&self) -> String {
+        let cells: Vec<Vec<String>> = self.rows.iter()
+            .map(|row| row.iter().map(markdown_cell).collect()).collect();
+        let widths: Vec<usize> = self.columns.iter().enumerate().map(|(n, (_, title))| {
+            cells.iter().map(|row| row[n].chars().count())
+                .fold(title.chars().count(), usize::max)
+        }).collect();
+        let mut ret = String::new();
+        let titles: Vec<String> = self.columns.iter().zip(widths.iter())
+            .map(|((_, title), &w)| format!("{:<1$}", title, w)).collect();
+        writeln!(ret, "{}", titles.join(" | ").trim_end()).unwrap();
+        let rules: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+        writeln!(ret, "{}", rules.join(" | ")).unwrap();
+        for (row, cells) in self.rows.iter().zip(cells.iter()) {
+            let cells: Vec<String> = row.iter().zip(cells.iter()).zip(widths.iter())
+                .map(|((value, cell), &w)| match value {
+                    Value::Str(_) => format!("{:<1$}", cell, w),
+                    _ => format!("{:>1$}", cell, w),
+                }).collect();
+            writeln!(ret, "{}", cells.join(" | ").trim_end()).unwrap();
+        }
+        ret
+    }
+    /// Returns an ordinary CSV table, with a header row of column keys.
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::new();
+        let keys: Vec<String> = self.columns.iter().map(|(k, _)| csv_field(k)).collect();
+        writeln!(ret, "{}", keys.join(",")).unwrap();
+        for row in self.rows.iter() {
+            let cells: Vec<String> = row.iter().map(|value| match value {
+                Value::Str(x) => csv_field(x),
+                Value::Null => String::new(),
+                _ => {
+                    let mut ret = String::new();
+                    write_scalar(&mut ret, value);
+                    ret
+                },
+            }).collect();
+            writeln!(ret, "{}", cells.join(",")).unwrap();
+        }
+        ret
+    }
+    /// Returns the table as a list of objects, one per row.
+    pub fn to_value(&self) -> Value {
+        Value::Array(self.rows.iter().map(|row| {
+            Value::Object(self.columns.iter().zip(row.iter())
+                          .map(|((k, _), v)| (k.clone(), v.clone())).collect())
+        }).collect())
+    }
+}
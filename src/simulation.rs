@@ -0,0 +1,690 @@
+use rand::{Rng,SeedableRng};
+use std::collections::BTreeMap;
+use std::sync::{Mutex,OnceLock};
+use std::time::{Duration,Instant};
+
+use crate::{PowerLevel, Weapon, Government};
+use crate::capture_odds::CaptureOdds;
+use crate::doomsday_clock::DoomsdayClock;
+use crate::exact::ExactSolution;
+use crate::optimal::OptimalPolicy;
+use crate::equilibrium::Equilibrium;
+use crate::defense::DefenseSolution;
+use crate::strategy::*;
+use crate::statistics::{Histogram, distribution_quantile};
+
+/// One ship in a boarding action.
+#[derive(Clone,Debug)]
+pub struct Side {
+    pub crew: u32,
+    pub government: Government,
+    /// Capture weapons, and how many of each. Add more with `add_weapon`,
+    /// so that `weapon_names` keeps up.
+    pub weapons: Vec<(Weapon, u32)>,
+    /// What each of `weapons` is called, in the same order.
+    pub weapon_names: Vec<String>,
+}
+
+impl Side {
+    pub fn new(crew: u32, government: Government,
+               weapons: Vec<(String, Weapon, u32)>) -> Side {
+        let mut ret = Side { crew, government, weapons: Vec::new(), weapon_names: Vec::new() };
+        for (name, weapon, count) in weapons.into_iter() {
+            ret.add_weapon(&name, weapon, count);
+        }
+        ret
+    }
+    pub fn add_weapon(&mut self, name: &str, weapon: Weapon, count: u32) {
+        self.weapons.push((weapon, count));
+        self.weapon_names.push(name.to_owned());
+    }
+    /// Returns this side's strength for every number of remaining crew, as
+    /// `calculate_strengths` works it out.
+    pub fn strengths(&self) -> Vec<PowerLevel> {
+        calculate_strengths(self.crew, self.government, &self.weapons)
+    }
+    /// Returns which weapon each of this side's crew fights with.
+    pub fn allocation(&self) -> Allocation {
+        Allocation::new(self.crew, self.government, &self.weapons)
+    }
+}
+
+/// The player's ship (`you`) boarding an enemy ship (`them`).
+#[derive(Clone,Debug)]
+pub struct Scenario {
+    pub you: Side,
+    pub them: Side,
+    pub enemy_rule: EnemyRule,
+}
+
+impl Scenario {
+    /// Starts out with the enemy following the game's AI.
+    pub fn new(you: Side, them: Side) -> Scenario {
+        Scenario { you, them, enemy_rule: EnemyRule::default() }
+    }
+    /// Works out both sides' strengths and the odds tables for the fight.
+    /// There's no fight unless both sides have crew.
+    pub fn prepare(&self) -> Result<Boarding, String> {
+        if self.you.crew == 0 { return Err("Your ship has no crew".to_owned()) }
+        if self.them.crew == 0 { return Err("The enemy ship has no crew".to_owned()) }
+        let ustrengths = self.you.strengths();
+        let mstrengths = self.them.strengths();
+        Ok(Boarding {
+            ucrew: self.you.crew,
+            mcrew: self.them.crew,
+            ustrength: *ustrengths.last().unwrap(),
+            mstrength: *mstrengths.last().unwrap(),
+            player_attack_odds: CaptureOdds::new(&ustrengths, &mstrengths),
+            player_defense_odds: CaptureOdds::new(&mstrengths, &ustrengths),
+            enemy_rule: self.enemy_rule,
+        })
+    }
+}
+
+/// Everything needed to simulate or solve a boarding action.
+pub struct Boarding {
+    pub ucrew: u32,
+    pub mcrew: u32,
+    /// Your total strength, with your whole crew.
+    pub ustrength: PowerLevel,
+    /// Their total strength, with their whole crew.
+    pub mstrength: PowerLevel,
+    pub player_attack_odds: CaptureOdds,
+    pub player_defense_odds: CaptureOdds,
+    pub enemy_rule: EnemyRule,
+}
+
+impl Boarding {
+    /// The odds the game gives you of capturing their ship.
+    pub fn game_victory_odds(&self) -> f64 {
+        self.player_attack_odds.capture_odds(self.ucrew, self.mcrew)
+    }
+    /// The casualties the game expects you to take capturing their ship.
+    pub fn game_victory_casualties(&self) -> f64 {
+        self.player_attack_odds.attacker_casualties(self.ucrew, self.mcrew)
+    }
+    /// The spread of the casualties the game expects you to take capturing
+    /// their ship: the standard deviation, and the range the middle 90% of
+    /// fights fall in.
+    pub fn game_victory_casualty_spread(&self) -> (f64, u32, u32) {
+        let odds = &self.player_attack_odds;
+        let casualties = odds.terminal_distribution(self.ucrew, self.mcrew)
+            .attacker_casualties();
+        (odds.attacker_casualty_variance(self.ucrew, self.mcrew).sqrt(),
+         distribution_quantile(&casualties, 0.05),
+         distribution_quantile(&casualties, 0.95))
+    }
+    /// The odds the game gives them of capturing your ship.
+    pub fn game_defeat_odds(&self) -> f64 {
+        self.player_defense_odds.capture_odds(self.mcrew, self.ucrew)
+    }
+    /// The casualties the game expects you to take while they capture your
+    /// ship.
+    pub fn game_defeat_casualties(&self) -> f64 {
+        self.player_defense_odds.defender_casualties(self.mcrew, self.ucrew)
+    }
+    /// The spread of the casualties the game expects you to take if they
+    /// try to capture your ship, like `game_victory_casualty_spread`.
+    pub fn game_defeat_casualty_spread(&self) -> (f64, u32, u32) {
+        let odds = &self.player_defense_odds;
+        let casualties = odds.terminal_distribution(self.mcrew, self.ucrew)
+            .defender_casualties();
+        (odds.defender_casualty_variance(self.mcrew, self.ucrew).sqrt(),
+         distribution_quantile(&casualties, 0.05),
+         distribution_quantile(&casualties, 0.95))
+    }
+    /// Solves the fight exactly with the given strategy.
+    pub fn exact_solution(&self, strategy: &dyn Strategy) -> ExactSolution {
+        ExactSolution::new(self.ucrew, self.mcrew, &self.player_attack_odds,
+                           &self.player_defense_odds, self.enemy_rule, strategy)
+    }
+    /// The exact odds of victory with the given strategy.
+    pub fn exact_victory_odds(&self, strategy: &dyn Strategy) -> f64 {
+        self.exact_solution(strategy).victory_odds(self.ucrew, self.mcrew)
+    }
+    /// Works out the best policy against the enemy's rule.
+    pub fn optimal_policy(&self) -> OptimalPolicy {
+        OptimalPolicy::new(self.ucrew, self.mcrew, &self.player_attack_odds,
+                           &self.player_defense_odds, self.enemy_rule)
+    }
+    /// Works out how both sides should play if the enemy were out to stop
+    /// you, instead of following a rule.
+    pub fn equilibrium(&self) -> Equilibrium {
+        Equilibrium::new(self.ucrew, self.mcrew, &self.player_attack_odds,
+                         &self.player_defense_odds)
+    }
+    /// Works out the odds if they board you instead, and you follow the
+    /// given strategy.
+    pub fn defense(&self, strategy: &dyn Strategy) -> DefenseSolution {
+        DefenseSolution::with_strategy(self.ucrew, self.mcrew, &self.player_attack_odds,
+                                       &self.player_defense_odds, self.enemy_rule,
+                                       strategy)
+    }
+    /// Works out the odds if they board you instead, and you do your best to
+    /// keep your ship.
+    pub fn optimal_defense(&self) -> DefenseSolution {
+        DefenseSolution::optimal(self.ucrew, self.mcrew, &self.player_attack_odds,
+                                 &self.player_defense_odds, self.enemy_rule)
+    }
+    /// Simulates the given number of boarding actions with each strategy.
+    pub fn run_trials<R: Rng>(&self, rng: &mut R, trials: usize,
+                              strategies: &[Box<dyn Strategy>]) -> Tally {
+        let mut ret = Tally::new(strategies.len());
+        ret.trials = trials;
+        for _ in 0 .. trials {
+            for (n, strategy) in strategies.iter().enumerate() {
+                let ending = sub_attempt(rng, self, strategy.as_ref());
+                match ending.outcome {
+                    Outcome::Victory => {
+                        ret.victories[n] += 1;
+                        ret.ucrew_after_victory[n].add(ending.ucrew);
+                    },
+                    Outcome::Defeat => ret.defeats[n] += 1,
+                    Outcome::Retreat => {
+                        ret.retreats[n] += 1;
+                        ret.retreat_crew[n] += ending.ucrew as u64;
+                    },
+                }
+                ret.ucrew_left[n].add(ending.ucrew);
+                ret.mcrew_left[n].add(ending.mcrew);
+            }
+        }
+        ret
+    }
+}
+
+/// The results of some number of simulated boarding actions.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Tally {
+    /// How many work units these results came from, if any.
+    pub units: usize,
+    /// How many boarding actions were simulated with each strategy.
+    pub trials: usize,
+    /// How many of them each strategy won, in the order the strategies were
+    /// given.
+    pub victories: Vec<usize>,
+    /// How many of them each strategy lost.
+    pub defeats: Vec<usize>,
+    /// How many of them each strategy broke off.
+    pub retreats: Vec<usize>,
+    /// The total crew each strategy brought back from its retreats.
+    pub retreat_crew: Vec<u64>,
+    /// How much crew you had left at the end, for each strategy.
+    pub ucrew_left: Vec<Histogram>,
+    /// How much crew you had left after each victory, for each strategy.
+    pub ucrew_after_victory: Vec<Histogram>,
+    /// How much crew they had left at the end, for each strategy.
+    pub mcrew_left: Vec<Histogram>,
+}
+
+fn add_counts<T: Copy + Default + std::ops::AddAssign>(a: &mut Vec<T>, b: &[T]) {
+    if a.len() < b.len() { a.resize(b.len(), T::default()) }
+    for (a, &b) in a.iter_mut().zip(b.iter()) { *a += b }
+}
+
+fn add_histograms(a: &mut Vec<Histogram>, b: &[Histogram]) {
+    if a.len() < b.len() { a.resize(b.len(), Histogram::default()) }
+    for (a, b) in a.iter_mut().zip(b.iter()) { *a += b }
+}
+
+impl std::ops::AddAssign<&Tally> for Tally {
+    fn add_assign(&mut self, rhs: &Tally) {
+        self.units += rhs.units;
+        self.trials += rhs.trials;
+        add_counts(&mut self.victories, &rhs.victories);
+        add_counts(&mut self.defeats, &rhs.defeats);
+        add_counts(&mut self.retreats, &rhs.retreats);
+        add_counts(&mut self.retreat_crew, &rhs.retreat_crew);
+        add_histograms(&mut self.ucrew_left, &rhs.ucrew_left);
+        add_histograms(&mut self.ucrew_after_victory, &rhs.ucrew_after_victory);
+        add_histograms(&mut self.mcrew_left, &rhs.mcrew_left);
+    }
+}
+
+impl Tally {
+    /// An empty tally for the given number of strategies.
+    pub fn new(strategies: usize) -> Tally {
+        Tally {
+            units: 0,
+            trials: 0,
+            victories: vec![0; strategies],
+            defeats: vec![0; strategies],
+            retreats: vec![0; strategies],
+            retreat_crew: vec![0; strategies],
+            ucrew_left: vec![Histogram::default(); strategies],
+            ucrew_after_victory: vec![Histogram::default(); strategies],
+            mcrew_left: vec![Histogram::default(); strategies],
+        }
+    }
+    /// The average crew each strategy brought back from its retreats, or
+    /// `None` if it never retreated.
+    pub fn mean_retreat_crew(&self, strategy: usize) -> Option<f64> {
+        if self.retreats[strategy] == 0 { None }
+        else { Some(self.retreat_crew[strategy] as f64 / self.retreats[strategy] as f64) }
+    }
+    /// Adds up the results of several threads.
+    pub fn total(tallies: &[Tally]) -> Tally {
+        tallies.iter().fold(Tally::default(), |mut a, b| { a += b; a })
+    }
+}
+
+pub fn calculate_strengths(crew: u32, gov: Government, weps: &[(Weapon, u32)]) -> Vec<PowerLevel> {
+    let length = weps.iter().map(|x| x.1).sum::<u32>();
+    let mut attack_strengths = Vec::with_capacity(length as usize);
+    let mut defense_strengths = Vec::with_capacity(length as usize);
+    for wep in weps {
+        let strength = wep.0 + gov;
+        for _ in 0 .. wep.1 {
+            attack_strengths.push(strength.attack_strength);
+            defense_strengths.push(strength.defense_strength);
+        }
+    }
+    debug_assert_eq!(attack_strengths.len(), defense_strengths.len());
+    while attack_strengths.len() < crew as usize {
+        attack_strengths.push(gov.attack_strength);
+        defense_strengths.push(gov.defense_strength);
+    }
+    // sort in descending order
+    attack_strengths.sort_by(|a,b| b.partial_cmp(a).unwrap());
+    defense_strengths.sort_by(|a,b| b.partial_cmp(a).unwrap());
+    // convert to running sums
+    for n in 1 .. attack_strengths.len() { attack_strengths[n] += attack_strengths[n-1]; }
+    for n in 1 .. defense_strengths.len() { defense_strengths[n] += defense_strengths[n-1]; }
+    attack_strengths.truncate(crew as usize);
+    defense_strengths.truncate(crew as usize);
+    attack_strengths.into_iter().zip(defense_strengths).map(|(a,d)| PowerLevel::new(a,d)).collect()
+}
+
+/// Which weapon each crew member fights with, the way `calculate_strengths`
+/// hands them out. Attack and defense are handed out separately, so the same
+/// crew member may attack with one weapon and defend with another.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Allocation {
+    /// For each crew member, in the order the strengths add them up (so the
+    /// last is the first to go), the index in the weapon list of what they
+    /// attack with (or `None` if there's nothing left for them), and their
+    /// attack strength with it.
+    pub attack: Vec<(Option<usize>, f64)>,
+    /// The same, for defense.
+    pub defense: Vec<(Option<usize>, f64)>,
+    /// How many of each weapon nobody uses, for attack or for defense.
+    pub unused: Vec<u32>,
+}
+
+impl Allocation {
+    pub fn new(crew: u32, gov: Government, weps: &[(Weapon, u32)]) -> Allocation {
+        // this has to sort the same way calculate_strengths does, ties and
+        // all, so that it picks the same weapons
+        let hand_out = |strength: &dyn Fn(PowerLevel) -> f64| {
+            let mut ret = Vec::new();
+            for (n, wep) in weps.iter().enumerate() {
+                let each = strength(wep.0 + gov);
+                for _ in 0 .. wep.1 { ret.push((Some(n), each)) }
+            }
+            while ret.len() < crew as usize { ret.push((None, strength(gov))) }
+            ret.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            ret.truncate(crew as usize);
+            ret
+        };
+        let attack = hand_out(&|x| x.attack_strength);
+        let defense = hand_out(&|x| x.defense_strength);
+        let used = |slots: &[(Option<usize>, f64)], n| {
+            slots.iter().filter(|x| x.0 == Some(n)).count() as u32
+        };
+        let unused = weps.iter().enumerate().map(|(n, wep)| {
+            wep.1 - used(&attack, n).max(used(&defense, n))
+        }).collect();
+        Allocation { attack, defense, unused }
+    }
+    /// What the last crew member adds to the side's attack and defense
+    /// strengths, which is also what the first one lost takes away.
+    pub fn last_crew_strength(&self) -> PowerLevel {
+        PowerLevel::new(self.attack.last().map(|x| x.1).unwrap_or(0.0),
+                        self.defense.last().map(|x| x.1).unwrap_or(0.0))
+    }
+}
+
+/// Returns the strategy's decision and whether the enemy attacks. If
+/// neither side attacks, the boarding action is over.
+pub fn choose_actions(ucrew: u32, mcrew: u32,
+                      player_attack_odds: &CaptureOdds,
+                      player_defense_odds: &CaptureOdds,
+                      enemy_rule: EnemyRule,
+                      strategy: &dyn Strategy)
+                      -> (Decision, bool) {
+    let situation = Situation { ucrew, mcrew, player_attack_odds, player_defense_odds, enemy_rule };
+    (strategy.decide(&situation), situation.enemy_attacks())
+}
+
+/// Returns your power and the total power in one round of fighting. Your
+/// odds of winning the round are the ratio of the two.
+pub fn round_powers(ucrew: u32, mcrew: u32, you_attack: bool, enemy_attacks: bool,
+                    player_attack_odds: &CaptureOdds,
+                    player_defense_odds: &CaptureOdds)
+                    -> (f64, f64) {
+    let upow = if you_attack {
+        player_attack_odds.attacker_power(ucrew)
+    }
+    else {
+        player_defense_odds.defender_power(ucrew)
+    };
+    let mpow = if enemy_attacks {
+        player_defense_odds.attacker_power(mcrew)
+    }
+    else {
+        player_attack_odds.defender_power(mcrew)
+    };
+    let tpow = upow + mpow;
+    debug_assert!(tpow > 0.0);
+    (upow, tpow)
+}
+
+/// Whether anyone fights this round. If you retreat, or neither side
+/// attacks, the boarding action is over.
+pub fn round_is_fought(action: Action, enemy_attacks: bool) -> bool {
+    match action {
+        Action::Attack => true,
+        Action::Defend => enemy_attacks,
+        Action::Retreat => false,
+    }
+}
+
+/// How a boarding action ended.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Outcome {
+    /// You captured their ship.
+    Victory,
+    /// You ran out of crew.
+    Defeat,
+    /// You went back to your ship.
+    Retreat,
+}
+
+/// How a boarding action ended, and who was left.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Ending {
+    pub outcome: Outcome,
+    pub ucrew: u32,
+    pub mcrew: u32,
+}
+
+/// Simulates one boarding action from the start.
+pub fn sub_attempt<R: Rng>(rng: &mut R, boarding: &Boarding, strategy: &dyn Strategy)
+                           -> Ending {
+    let (mut ucrew, mut mcrew) = (boarding.ucrew, boarding.mcrew);
+    let player_attack_odds = &boarding.player_attack_odds;
+    let player_defense_odds = &boarding.player_defense_odds;
+    while ucrew > 1 && mcrew > 0 {
+        let (decision, enemy_attacks) = choose_actions(ucrew, mcrew, player_attack_odds, player_defense_odds, boarding.enemy_rule, strategy);
+        let you_attack = decision.action == Action::Attack;
+        // as in the game, if nobody attacks, you go back to your ship
+        if !round_is_fought(decision.action, enemy_attacks) {
+            return Ending { outcome: Outcome::Retreat, ucrew, mcrew }
+        }
+        for _ in 0 .. decision.rounds.max(1) {
+            if ucrew == 0 || mcrew == 0 { break }
+            let (upow, tpow) = round_powers(ucrew, mcrew, you_attack, enemy_attacks, player_attack_odds, player_defense_odds);
+            if rng.gen::<f64>() * tpow >= upow { ucrew -= 1 }
+            else { mcrew -= 1 }
+        }
+    }
+    let outcome = if mcrew == 0 { Outcome::Victory } else { Outcome::Defeat };
+    Ending { outcome, ucrew, mcrew }
+}
+
+/// Returns the random number generator for the given work unit. Every work
+/// unit gets its own stream, so the results of a run depend only on the seed,
+/// and not on which threads happened to run which units.
+pub fn work_unit_rng(seed: u64, unit: usize) -> rand::rngs::StdRng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&(unit as u64).to_le_bytes());
+    rand::rngs::StdRng::from_seed(key)
+}
+
+fn thread_worker<F: FnMut() -> Option<usize>>(boarding: &Boarding,
+                                              strategies: &[Box<dyn Strategy>],
+                                              icount: usize, seed: u64,
+                                              mut should_continue: F)
+                                              -> Tally {
+    let mut ret = Tally::new(strategies.len());
+    while let Some(unit) = should_continue() {
+        let mut rng = work_unit_rng(seed, unit);
+        ret += &boarding.run_trials(&mut rng, icount, strategies);
+        ret.units += 1;
+    }
+    ret
+}
+
+static PROGRESS_HOOK: OnceLock<fn(usize)> = OnceLock::new();
+
+/// Sets what to do with the countdown while long jobs run. About once a
+/// second, the calling thread passes it the number of work units (or other
+/// jobs) left, or for open-ended runs, the number done so far. Nothing is
+/// done with it until this is called, and it can only be called once.
+pub fn set_progress_hook(hook: fn(usize)) {
+    let _ = PROGRESS_HOOK.set(hook);
+}
+
+fn report_progress(count: usize) {
+    if let Some(hook) = PROGRESS_HOOK.get() { hook(count) }
+}
+
+/// How to spread work units over threads.
+#[derive(Clone,Copy,Debug)]
+pub struct WorkPlan {
+    /// The number of trials in each work unit.
+    pub icount: usize,
+    /// The number of threads to use, including the calling thread.
+    pub tcount: usize,
+    /// Use the thread pool even if there's only one thread.
+    pub force_threaded: bool,
+}
+
+/// Runs the given number of work units, numbered starting from `first_unit`,
+/// and returns what each thread did. The first entry is the calling thread,
+/// which reports the countdown while it works.
+pub fn run_work_units(boarding: &Boarding, strategies: &[Box<dyn Strategy>],
+                      plan: WorkPlan, seed: u64,
+                      first_unit: usize, units: usize) -> Vec<Tally> {
+    let icount = plan.icount;
+    if plan.tcount > 1 || plan.force_threaded {
+        let remaining_work_units = DoomsdayClock::new(units);
+        std::thread::scope(|scope| {
+            let mut threads = Vec::with_capacity(plan.tcount - 1);
+            for n in 1 .. plan.tcount {
+                let remaining_work_units = &remaining_work_units;
+                threads.push(std::thread::Builder::new()
+                    .name(format!("worker thread {}", n))
+                    .spawn_scoped(scope, move || {
+                        thread_worker(boarding, strategies, icount, seed, || remaining_work_units.tick().map(|x| x + first_unit))
+                    }).unwrap());
+            }
+            let mut ret = Vec::with_capacity(plan.tcount);
+            let mut last = Instant::now();
+            ret.push(thread_worker(boarding, strategies, icount, seed, || {
+                let unit = remaining_work_units.tick()?;
+                if last.elapsed() >= Duration::new(1,0) {
+                    last = Instant::now();
+                    report_progress(unit + 1);
+                }
+                Some(unit + first_unit)
+            }));
+            for thread in threads.into_iter() {
+                ret.push(match thread.join() {
+                    Ok(x) => x,
+                    Err(x) => {
+                        panic!("A thread panicked, so will we!\n{:?}", x);
+                    },
+                });
+            }
+            ret
+        })
+    }
+    else {
+        let mut it = first_unit .. first_unit + units;
+        vec![thread_worker(boarding, strategies, icount, seed, || it.next())]
+    }
+}
+
+/// Runs work units, numbered from 0, until `enough` is happy with the total so
+/// far, and returns what each thread did, the same as `run_work_units`.
+/// `enough` is checked after every unit, on the total of that unit and every
+/// one before it, and any units still being run when it's happy are thrown
+/// away. That way the results depend only on the seed, and not on how many
+/// threads there are.
+pub fn run_work_units_until<F>(boarding: &Boarding, strategies: &[Box<dyn Strategy>],
+                               plan: WorkPlan, seed: u64, enough: F) -> Vec<Tally>
+where F: Fn(&Tally) -> bool + Sync {
+    struct Progress {
+        total: Tally,
+        threads: Vec<Tally>,
+        /// The first unit that isn't in `total` yet.
+        next: usize,
+        /// Units that finished before some unit ahead of them.
+        waiting: BTreeMap<usize, (usize, Tally)>,
+        done: bool,
+    }
+    let tcount = if plan.tcount > 1 || plan.force_threaded { plan.tcount } else { 1 };
+    // the clock counts down, so units are handed out from the top
+    let remaining_work_units = DoomsdayClock::new(usize::MAX);
+    let progress = Mutex::new(Progress {
+        total: Tally::new(strategies.len()),
+        threads: vec![Tally::new(strategies.len()); tcount],
+        next: 0,
+        waiting: BTreeMap::new(),
+        done: false,
+    });
+    let worker = |thread: usize| {
+        let mut last = Instant::now();
+        while let Some(x) = remaining_work_units.tick() {
+            let unit = usize::MAX - 1 - x;
+            let mut tally = boarding.run_trials(&mut work_unit_rng(seed, unit), plan.icount, strategies);
+            tally.units = 1;
+            let mut progress = progress.lock().unwrap();
+            if progress.done { return }
+            progress.waiting.insert(unit, (thread, tally));
+            loop {
+                let next = progress.next;
+                let (thread, tally) = match progress.waiting.remove(&next) {
+                    Some(x) => x,
+                    None => break,
+                };
+                progress.next += 1;
+                progress.total += &tally;
+                progress.threads[thread] += &tally;
+                if enough(&progress.total) {
+                    remaining_work_units.stop();
+                    progress.done = true;
+                    progress.waiting.clear();
+                    return
+                }
+            }
+            if thread == 0 && last.elapsed() >= Duration::new(1,0) {
+                last = Instant::now();
+                report_progress(progress.next);
+            }
+        }
+    };
+    std::thread::scope(|scope| {
+        let mut threads = Vec::with_capacity(tcount - 1);
+        for n in 1 .. tcount {
+            let worker = &worker;
+            threads.push(std::thread::Builder::new()
+                .name(format!("worker thread {}", n))
+                .spawn_scoped(scope, move || worker(n)).unwrap());
+        }
+        worker(0);
+        for thread in threads.into_iter() {
+            if let Err(x) = thread.join() {
+                panic!("A thread panicked, so will we!\n{:?}", x);
+            }
+        }
+    });
+    progress.into_inner().unwrap().threads
+}
+
+/// Calls `f` for every index from 0 up to (but not including) `count`, spread
+/// over `tcount` threads, and returns the results in index order. The calling
+/// thread is one of the threads, and reports the countdown while it works.
+pub fn parallel_map<T, F>(tcount: usize, count: usize, f: F) -> Vec<T>
+where T: Send, F: Fn(usize) -> T + Sync {
+    let remaining = DoomsdayClock::new(count);
+    let worker = |loud: bool| {
+        let mut ret = Vec::new();
+        let mut last = Instant::now();
+        while let Some(index) = remaining.tick() {
+            if loud && last.elapsed() >= Duration::new(1,0) {
+                last = Instant::now();
+                report_progress(index + 1);
+            }
+            ret.push((index, f(index)));
+        }
+        ret
+    };
+    let mut results = std::thread::scope(|scope| {
+        let mut threads = Vec::with_capacity(tcount.max(1) - 1);
+        for n in 1 .. tcount {
+            threads.push(std::thread::Builder::new()
+                .name(format!("worker thread {}", n))
+                .spawn_scoped(scope, || worker(false)).unwrap());
+        }
+        let mut results = worker(true);
+        for thread in threads.into_iter() {
+            match thread.join() {
+                Ok(x) => results.extend(x),
+                Err(x) => {
+                    panic!("A thread panicked, so will we!\n{:?}", x);
+                },
+            }
+        }
+        results
+    });
+    results.sort_by_key(|x| x.0);
+    results.into_iter().map(|x| x.1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_crew() {
+        let side = |crew| Side::new(crew, Government::new(1.0, 2.0), Vec::new());
+        assert!(Scenario::new(side(0), side(3)).prepare().is_err());
+        assert!(Scenario::new(side(3), side(0)).prepare().is_err());
+        assert!(Scenario::new(side(1), side(1)).prepare().is_ok());
+    }
+
+    #[test]
+    fn same_seed_same_results() {
+        let side = |crew| Side::new(crew, Government::new(1.0, 2.0), Vec::new());
+        let boarding = Scenario::new(side(12), side(9)).prepare().unwrap();
+        let strategies = default_strategies();
+        let plan = |tcount| WorkPlan { icount: 50, tcount, force_threaded: false };
+        let fixed = |tcount| Tally::total(&run_work_units(&boarding, &strategies, plan(tcount), 1234, 0, 40));
+        let until = |tcount| Tally::total(&run_work_units_until(&boarding, &strategies, plan(tcount), 1234, |total| total.victories[0] >= 500));
+        let one = fixed(1);
+        assert_eq!(one.units, 40);
+        assert_eq!(one, fixed(4));
+        let one = until(1);
+        assert!(one.victories[0] >= 500 && one.units < 40);
+        assert_eq!(one, until(4));
+    }
+
+    #[test]
+    fn histograms_count_every_trial() {
+        let side = |crew| Side::new(crew, Government::new(1.0, 2.0), Vec::new());
+        let boarding = Scenario::new(side(12), side(9)).prepare().unwrap();
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(AutoGo), Box::new(RetreatBelowCrew(Box::new(OneAtATime), 6))];
+        let plan = WorkPlan { icount: 50, tcount: 3, force_threaded: false };
+        let tally = Tally::total(&run_work_units(&boarding, &strategies, plan, 1, 0, 20));
+        assert_eq!(tally.trials, 1000);
+        for n in 0 .. strategies.len() {
+            assert_eq!(tally.ucrew_left[n].total(), tally.trials);
+            assert_eq!(tally.mcrew_left[n].total(), tally.trials);
+            assert_eq!(tally.ucrew_after_victory[n].total(), tally.victories[n]);
+            assert_eq!(tally.victories[n] + tally.defeats[n] + tally.retreats[n], tally.trials);
+        }
+    }
+}
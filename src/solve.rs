@@ -0,0 +1,119 @@
+use crate::simulation::Scenario;
+use crate::strategy::Strategy;
+use crate::sweep::{Axis, Parameter};
+
+/// Where the victory odds cross a target, along one axis.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Threshold {
+    /// The axis value that just reaches the target, or `None` if no value
+    /// on the axis does.
+    pub value: Option<u32>,
+    /// The exact victory odds at `value`.
+    pub odds: Option<f64>,
+    /// The value on the axis before `value`, and its odds, if there is one.
+    pub below: Option<(u32, f64)>,
+    /// The value on the axis after `value`, and its odds, if there is one.
+    pub above: Option<(u32, f64)>,
+    /// Whether the search had to fall back to solving every value on the
+    /// axis, because the odds didn't only get better (or worse) along it.
+    pub scanned: bool,
+}
+
+/// Whether more of this parameter is good for you.
+pub fn helps_you(parameter: &Parameter) -> bool {
+    match *parameter {
+        Parameter::UCrew | Parameter::UWeapon(_) => true,
+        Parameter::MCrew | Parameter::MWeapon(_) => false,
+        Parameter::EnemyRule(_) => panic!("enemy rules can't be solved for"),
+    }
+}
+
+/// Searches the axis for where the exact victory odds with `strategy` reach
+/// `target`. For things that help you, this is the smallest value that
+/// reaches it; for things that help them, the largest.
+///
+/// The odds usually only get better (or worse) along the axis, so this
+/// starts with a binary search, which only has to solve a handful of points.
+/// They don't always, though: auto-go fights a fifth of your crew's worth of
+/// rounds at a time, so one more crew member can make your odds worse. If
+/// any of the points the search solved, or the ones either side of its
+/// answer, are out of order, it falls back to solving every value. Fails if
+/// any point on the axis can't be prepared.
+pub fn solve_threshold(base: &Scenario, axis: &Axis, strategy: &dyn Strategy,
+                       target: f64) -> Result<Threshold, String> {
+    let len = axis.values.len();
+    let increasing = helps_you(&axis.parameter);
+    // the odds at every point that's been solved so far
+    let mut solved: Vec<Option<f64>> = vec![None; len];
+    let odds_at = |solved: &mut Vec<Option<f64>>, n: usize| -> Result<f64, String> {
+        if let Some(x) = solved[n] { return Ok(x) }
+        let mut scenario = base.clone();
+        axis.parameter.apply(&mut scenario, axis.values[n]);
+        let odds = scenario.prepare()?.exact_victory_odds(strategy);
+        solved[n] = Some(odds);
+        Ok(odds)
+    };
+    // find the first index on the losing side of the target, counting from
+    // the end where the odds are best
+    let at = |n: usize| if increasing { len - 1 - n } else { n };
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if odds_at(&mut solved, at(mid))? >= target { lo = mid + 1 }
+        else { hi = mid }
+    }
+    let mut found = if lo == 0 { None } else { Some(at(lo - 1)) };
+    if let Some(n) = found {
+        // these are reported anyway
+        if n > 0 { odds_at(&mut solved, n - 1)?; }
+        if n + 1 < len { odds_at(&mut solved, n + 1)?; }
+    }
+    let known: Vec<f64> = solved.iter().filter_map(|&x| x).collect();
+    let ordered = known.windows(2).all(|x| {
+        if increasing { x[0] <= x[1] + 1e-12 } else { x[0] + 1e-12 >= x[1] }
+    });
+    if !ordered {
+        found = None;
+        for n in 0 .. len {
+            if odds_at(&mut solved, n)? >= target {
+                found = Some(n);
+                if increasing { break }
+            }
+        }
+    }
+    let n = match found {
+        None => return Ok(Threshold { value: None, odds: None, below: None, above: None, scanned: !ordered }),
+        Some(n) => n,
+    };
+    let below = if n > 0 { Some((axis.values[n - 1], odds_at(&mut solved, n - 1)?)) } else { None };
+    let above = if n + 1 < len { Some((axis.values[n + 1], odds_at(&mut solved, n + 1)?)) } else { None };
+    Ok(Threshold {
+        value: Some(axis.values[n]),
+        odds: Some(odds_at(&mut solved, n)?),
+        below, above,
+        scanned: !ordered,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn auto_go_isnt_monotonic() {
+        // your odds with auto-go drop from 50 crew to 51, since 51 fights
+        // more rounds at a time, and don't get back to 0.3% until 54
+        let base = Scenario::new(Side::new(2, Government::new(1.0, 2.0), Vec::new()),
+                                 Side::new(60, Government::new(1.4, 2.6), Vec::new()));
+        let axis = Axis { name: "ucrew".to_owned(), parameter: Parameter::UCrew, values: (2 ..= 150).collect() };
+        let threshold = solve_threshold(&base, &axis, &AutoGo, 0.003).unwrap();
+        assert_eq!(threshold.value, Some(48));
+        assert!(threshold.scanned);
+        assert!(threshold.below.unwrap().1 < 0.003);
+        // one-at-a-time doesn't have the problem
+        let threshold = solve_threshold(&base, &axis, &OneAtATime, 0.003).unwrap();
+        assert_eq!(threshold.value, Some(46));
+        assert!(!threshold.scanned);
+    }
+}
@@ -0,0 +1,45 @@
+/// One value for every state of a fight, from (0, 0) up to a given pair of
+/// crew counts, indexed by `(ucrew, mcrew)`.
+///
+/// Every round of a fight removes someone, so the solvers fill these in from
+/// the smallest states upward: every `mcrew` for one `ucrew`, then the next
+/// `ucrew`, and so on. By the time a state is pushed, every state it can lead
+/// to is already there.
+#[derive(Clone,Debug)]
+pub struct StateTable<T> {
+    mcrew: u32,
+    values: Vec<T>,
+}
+
+impl<T> StateTable<T> {
+    /// An empty table, with room for every state up to `ucrew` and `mcrew`.
+    pub fn new(ucrew: u32, mcrew: u32) -> StateTable<T> {
+        StateTable {
+            mcrew,
+            values: Vec::with_capacity((ucrew as usize + 1) * (mcrew as usize + 1)),
+        }
+    }
+    /// Adds the value for the next state.
+    pub fn push(&mut self, value: T) {
+        self.values.push(value);
+    }
+    /// The largest `ucrew` filled in so far.
+    pub fn ucrew(&self) -> u32 {
+        (self.values.len() / (self.mcrew as usize + 1)) as u32 - 1
+    }
+    pub fn mcrew(&self) -> u32 {
+        self.mcrew
+    }
+    /// Every value, in the order they were pushed.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+}
+
+impl<T> std::ops::Index<(u32, u32)> for StateTable<T> {
+    type Output = T;
+    fn index(&self, (ucrew, mcrew): (u32, u32)) -> &T {
+        debug_assert!(mcrew <= self.mcrew);
+        &self.values[ucrew as usize * (self.mcrew as usize + 1) + mcrew as usize]
+    }
+}
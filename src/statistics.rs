@@ -0,0 +1,148 @@
+/// Returns the z-score below which the given fraction of a standard normal
+/// distribution lies. Uses Acklam's rational approximation, which is good to
+/// about nine significant digits; plenty for confidence intervals.
+pub fn normal_quantile(p: f64) -> f64 {
+    debug_assert!(p > 0.0 && p < 1.0);
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02,
+                         -2.759285104469687e+02, 1.38357751867269e+02,
+                         -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02,
+                         -1.556989798598866e+02, 6.680131188771972e+01,
+                         -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01,
+                         -2.400758277161838e+00, -2.549732539343734e+00,
+                         4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01,
+                         2.445134137142996e+00, 3.754408661907416e+00];
+    const LOW: f64 = 0.02425;
+    if p < LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0]*q+C[1])*q+C[2])*q+C[3])*q+C[4])*q+C[5])
+            / ((((D[0]*q+D[1])*q+D[2])*q+D[3])*q+1.0)
+    }
+    else if p <= 1.0 - LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0]*r+A[1])*r+A[2])*r+A[3])*r+A[4])*r+A[5])*q
+            / (((((B[0]*r+B[1])*r+B[2])*r+B[3])*r+B[4])*r+1.0)
+    }
+    else {
+        -normal_quantile(1.0 - p)
+    }
+}
+
+/// Returns the z-score for a two-sided confidence interval at the given
+/// confidence level, which is a fraction (e.g. 0.95).
+pub fn confidence_z(confidence: f64) -> f64 {
+    normal_quantile(0.5 + confidence * 0.5)
+}
+
+/// Returns the Wilson score interval for a binomial proportion, as a pair of
+/// fractions.
+pub fn wilson_interval(successes: usize, trials: usize, z: f64) -> (f64, f64) {
+    if trials == 0 { return (0.0, 1.0) }
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half_width = z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt()
+        / (1.0 + z2 / n);
+    ((center - half_width).max(0.0), (center + half_width).min(1.0))
+}
+
+/// Returns the smallest `n` such that `probabilities[0 ..= n]` add up to at
+/// least `q`.
+pub fn distribution_quantile(probabilities: &[f64], q: f64) -> u32 {
+    let mut seen = 0.0;
+    for (n, &p) in probabilities.iter().enumerate() {
+        seen += p;
+        // leave a little room for rounding error
+        if seen >= q - 1e-12 { return n as u32 }
+    }
+    probabilities.len().saturating_sub(1) as u32
+}
+
+/// Counts of how often each small whole number came up.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Histogram {
+    /// `counts[x]` is how many times `x` came up.
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn add(&mut self, x: u32) {
+        let x = x as usize;
+        if self.counts.len() <= x { self.counts.resize(x + 1, 0) }
+        self.counts[x] += 1;
+    }
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+    pub fn mean(&self) -> Option<f64> {
+        let total = self.total();
+        if total == 0 { return None }
+        let sum: f64 = self.counts.iter().enumerate()
+            .map(|(x, &n)| x as f64 * n as f64).sum();
+        Some(sum / total as f64)
+    }
+    /// Returns the smallest value that at least the given fraction of the
+    /// counts are at or below.
+    pub fn quantile(&self, q: f64) -> Option<u32> {
+        let total = self.total();
+        if total == 0 { return None }
+        let target = (q * total as f64).ceil().max(1.0) as usize;
+        let mut seen = 0;
+        for (x, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= target { return Some(x as u32) }
+        }
+        unreachable!()
+    }
+    /// How many of the counts are for values of at least `x`.
+    pub fn at_least(&self, x: u32) -> usize {
+        self.counts.iter().skip(x as usize).sum()
+    }
+}
+
+impl std::ops::AddAssign<&Histogram> for Histogram {
+    fn add_assign(&mut self, rhs: &Histogram) {
+        if self.counts.len() < rhs.counts.len() {
+            self.counts.resize(rhs.counts.len(), 0);
+        }
+        for (a, &b) in self.counts.iter_mut().zip(rhs.counts.iter()) { *a += b }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_quantiles() {
+        // from tables of the standard normal distribution, on both sides of
+        // where the approximation switches between its central and tail forms
+        for &(p, z) in [(0.5, 0.0), (0.975, 1.959963985), (0.9, 1.281551566),
+                        (0.03, -1.880793608), (0.02, -2.053748911),
+                        (0.97, 1.880793608), (0.98, 2.053748911),
+                        (0.001, -3.090232306), (0.999, 3.090232306)].iter() {
+            let actual = normal_quantile(p);
+            assert!((actual - z).abs() < 1e-8, "{}: got {}, expected {}", p, actual, z);
+        }
+        assert!((confidence_z(0.95) - 1.959963985).abs() < 1e-8);
+    }
+
+    #[test]
+    fn wilson_at_the_ends() {
+        let z = confidence_z(0.95);
+        let z2 = z * z;
+        // with no successes the interval starts at zero, and reaches up to
+        // z^2 / (n + z^2); all successes is the mirror image
+        let (low, high) = wilson_interval(0, 100, z);
+        assert_eq!(low, 0.0);
+        assert!((high - z2 / (100.0 + z2)).abs() < 1e-12);
+        let (low, high) = wilson_interval(100, 100, z);
+        assert!((low - 100.0 / (100.0 + z2)).abs() < 1e-12);
+        assert_eq!(high, 1.0);
+        assert_eq!(wilson_interval(0, 0, z), (0.0, 1.0));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::capture_odds::CaptureOdds;
+
+/// What the player can do in a round of boarding combat.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Action {
+    Attack,
+    Defend,
+    /// Go back to your own ship with whatever crew you have left.
+    Retreat,
+}
+
+/// What to do, and how many rounds to keep doing it before deciding again.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Decision {
+    pub action: Action,
+    pub rounds: u32,
+}
+
+/// How the enemy decides whether to attack each round.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum EnemyRule {
+    AlwaysAttack,
+    AlwaysDefend,
+    /// Attack when the game's odds of capturing you are better than this.
+    /// The game's AI uses 0.5.
+    Threshold(f64),
+    /// Attack when the game's odds of capturing you are better than your
+    /// odds of capturing it, the way the game advises the player.
+    Mirror,
+}
+
+impl Default for EnemyRule {
+    fn default() -> EnemyRule { EnemyRule::Threshold(0.5) }
+}
+
+impl EnemyRule {
+    /// A short name for reports, which can be given to `-enemy`.
+    pub fn name(&self) -> String {
+        match *self {
+            EnemyRule::AlwaysAttack => "always-attack".to_owned(),
+            EnemyRule::AlwaysDefend => "always-defend".to_owned(),
+            EnemyRule::Threshold(x) => format!("threshold:{}", x),
+            EnemyRule::Mirror => "mirror".to_owned(),
+        }
+    }
+}
+
+/// Parses an enemy rule name, as returned by `EnemyRule::name`. `game` is
+/// the game's own rule.
+pub fn parse_enemy_rule(s: &str) -> Option<EnemyRule> {
+    match s {
+        "game" => Some(EnemyRule::default()),
+        "always-attack" => Some(EnemyRule::AlwaysAttack),
+        "always-defend" => Some(EnemyRule::AlwaysDefend),
+        "mirror" => Some(EnemyRule::Mirror),
+        _ if s.starts_with("threshold:") => match s["threshold:".len()..].parse() {
+            Ok(x) if (0.0 ..= 1.0).contains(&x) => Some(EnemyRule::Threshold(x)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Everything a strategy gets to look at when it makes a decision.
+#[derive(Copy,Clone)]
+pub struct Situation<'a> {
+    pub ucrew: u32,
+    pub mcrew: u32,
+    pub player_attack_odds: &'a CaptureOdds,
+    pub player_defense_odds: &'a CaptureOdds,
+    pub enemy_rule: EnemyRule,
+}
+
+impl<'a> Situation<'a> {
+    /// The odds the game gives you of capturing them from here.
+    pub fn uatk_odds(&self) -> f64 {
+        self.player_attack_odds.capture_odds(self.ucrew, self.mcrew)
+    }
+    /// The odds the game gives them of capturing you from here.
+    pub fn udef_odds(&self) -> f64 {
+        self.player_defense_odds.capture_odds(self.mcrew, self.ucrew)
+    }
+    /// Whether the enemy will attack this round.
+    pub fn enemy_attacks(&self) -> bool {
+        match self.enemy_rule {
+            EnemyRule::AlwaysAttack => true,
+            EnemyRule::AlwaysDefend => false,
+            EnemyRule::Threshold(x) => self.udef_odds() > x,
+            EnemyRule::Mirror => self.udef_odds() > self.uatk_odds(),
+        }
+    }
+    /// The action the game's odds suggest: attack if your odds beat theirs,
+    /// or if they aren't going to attack.
+    pub fn game_style_action(&self) -> Action {
+        if self.uatk_odds() > self.udef_odds() || !self.enemy_attacks() {
+            Action::Attack
+        }
+        else { Action::Defend }
+    }
+    /// How many rounds auto-go fights before deciding again.
+    pub fn auto_go_rounds(&self) -> u32 {
+        (self.ucrew / 5).max(1)
+    }
+}
+
+/// A rule for choosing what to do in each round of a boarding action.
+///
+/// Decisions must depend only on the situation, so that the exact solver can
+/// work out a strategy's odds.
+pub trait Strategy: Send + Sync {
+    /// A short name for reports, which can be given to `-strategy`.
+    fn name(&self) -> String;
+    fn decide(&self, situation: &Situation) -> Decision;
+}
+
+impl std::fmt::Debug for dyn Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+/// Does what the game's odds suggest, fighting as many rounds at a time as
+/// the game does.
+pub struct AutoGo;
+
+impl Strategy for AutoGo {
+    fn name(&self) -> String { "auto-go".to_owned() }
+    fn decide(&self, situation: &Situation) -> Decision {
+        Decision {
+            action: situation.game_style_action(),
+            rounds: situation.auto_go_rounds(),
+        }
+    }
+}
+
+/// Does what the game's odds suggest, deciding again after every round.
+pub struct OneAtATime;
+
+impl Strategy for OneAtATime {
+    fn name(&self) -> String { "one-at-a-time".to_owned() }
+    fn decide(&self, situation: &Situation) -> Decision {
+        Decision { action: situation.game_style_action(), rounds: 1 }
+    }
+}
+
+/// Attacks every round, no matter what.
+pub struct AlwaysAttack;
+
+impl Strategy for AlwaysAttack {
+    fn name(&self) -> String { "always-attack".to_owned() }
+    fn decide(&self, _: &Situation) -> Decision {
+        Decision { action: Action::Attack, rounds: 1 }
+    }
+}
+
+/// Defends until the game's odds of capturing them exceed a threshold, then
+/// attacks. Attacks anyway if they aren't going to.
+pub struct AttackAbove(pub f64);
+
+impl Strategy for AttackAbove {
+    fn name(&self) -> String { format!("attack-above:{}", self.0) }
+    fn decide(&self, situation: &Situation) -> Decision {
+        let action = if situation.uatk_odds() > self.0 || !situation.enemy_attacks() {
+            Action::Attack
+        } else { Action::Defend };
+        Decision { action, rounds: 1 }
+    }
+}
+
+/// Uses auto-go until your crew drops below a threshold, then switches to
+/// one-at-a-time.
+pub struct AutoGoAbove(pub u32);
+
+impl Strategy for AutoGoAbove {
+    fn name(&self) -> String { format!("auto-go-above:{}", self.0) }
+    fn decide(&self, situation: &Situation) -> Decision {
+        if situation.ucrew >= self.0 { AutoGo.decide(situation) }
+        else { OneAtATime.decide(situation) }
+    }
+}
+
+/// Follows another strategy, but retreats once your crew drops below a
+/// threshold.
+pub struct RetreatBelowCrew(pub Box<dyn Strategy>, pub u32);
+
+impl Strategy for RetreatBelowCrew {
+    fn name(&self) -> String { format!("{}+retreat:{}", self.0.name(), self.1) }
+    fn decide(&self, situation: &Situation) -> Decision {
+        if situation.ucrew < self.1 {
+            Decision { action: Action::Retreat, rounds: 1 }
+        }
+        else { self.0.decide(situation) }
+    }
+}
+
+/// Follows another strategy, but retreats once the game's odds of capturing
+/// them drop below a threshold.
+pub struct RetreatBelowOdds(pub Box<dyn Strategy>, pub f64);
+
+impl Strategy for RetreatBelowOdds {
+    fn name(&self) -> String { format!("{}+retreat-odds:{}", self.0.name(), self.1) }
+    fn decide(&self, situation: &Situation) -> Decision {
+        if situation.uatk_odds() < self.1 {
+            Decision { action: Action::Retreat, rounds: 1 }
+        }
+        else { self.0.decide(situation) }
+    }
+}
+
+/// Returns the strategies to use when none are asked for.
+pub fn default_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![Box::new(AutoGo), Box::new(OneAtATime)]
+}
+
+/// Parses a strategy name, as returned by `Strategy::name`.
+pub fn parse_strategy(s: &str) -> Option<Box<dyn Strategy>> {
+    if let Some(x) = s.rfind('+') {
+        let inner = parse_strategy(&s[..x])?;
+        let (name, arg) = s[x+1..].split_at(s[x+1..].find(':')?);
+        return match (name, arg[1..].parse::<f64>()) {
+            ("retreat", _) => arg[1..].parse().ok()
+                .map(|x| Box::new(RetreatBelowCrew(inner, x)) as Box<dyn Strategy>),
+            ("retreat-odds", Ok(x)) if (0.0 ..= 1.0).contains(&x) => {
+                Some(Box::new(RetreatBelowOdds(inner, x)))
+            },
+            _ => None,
+        }
+    }
+    let (name, arg) = match s.find(':') {
+        Some(x) => (&s[..x], Some(&s[x+1..])),
+        None => (s, None),
+    };
+    match (name, arg) {
+        ("auto-go", None) => Some(Box::new(AutoGo)),
+        ("one-at-a-time", None) => Some(Box::new(OneAtATime)),
+        ("always-attack", None) => Some(Box::new(AlwaysAttack)),
+        ("attack-above", Some(x)) => match x.parse() {
+            Ok(x) if (0.0 ..= 1.0).contains(&x) => Some(Box::new(AttackAbove(x))),
+            _ => None,
+        },
+        ("auto-go-above", Some(x)) => match x.parse() {
+            Ok(x) => Some(Box::new(AutoGoAbove(x))),
+            Err(_) => None,
+        },
+        _ => None,
+    }
+}
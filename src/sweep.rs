@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use crate::simulation::*;
+use crate::strategy::{EnemyRule, Strategy};
+
+/// An inclusive range of values, written `START..END` or `START..END:STEP`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Range {
+    pub start: u32,
+    pub end: u32,
+    pub step: u32,
+}
+
+impl Range {
+    pub fn values(&self) -> Vec<u32> {
+        (self.start ..= self.end).step_by(self.step as usize).collect()
+    }
+}
+
+impl FromStr for Range {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, step) = match s.find(':') {
+            Some(x) => (&s[..x], s[x+1..].parse().map_err(|_| ())?),
+            None => (s, 1),
+        };
+        let s: Vec<&str> = range.split("..").collect();
+        if s.len() != 2 { return Err(()) }
+        let start = s[0].parse().map_err(|_| ())?;
+        let end = s[1].parse().map_err(|_| ())?;
+        if step == 0 || end < start { return Err(()) }
+        Ok(Range { start, end, step })
+    }
+}
+
+/// Something about a `Scenario` that a sweep can vary.
+#[derive(Clone,Debug,PartialEq)]
+pub enum Parameter {
+    UCrew,
+    MCrew,
+    /// The count of one of the player's weapons, by index.
+    UWeapon(usize),
+    /// The count of one of the enemy's weapons, by index.
+    MWeapon(usize),
+    /// The enemy's rule, by index into this list.
+    EnemyRule(Vec<EnemyRule>),
+}
+
+impl Parameter {
+    pub fn apply(&self, scenario: &mut Scenario, value: u32) {
+        match *self {
+            Parameter::UCrew => scenario.you.crew = value,
+            Parameter::MCrew => scenario.them.crew = value,
+            Parameter::UWeapon(n) => scenario.you.weapons[n].1 = value,
+            Parameter::MWeapon(n) => scenario.them.weapons[n].1 = value,
+            Parameter::EnemyRule(ref rules) => scenario.enemy_rule = rules[value as usize],
+        }
+    }
+    /// Whether this parameter's values are numbers, rather than indices.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(*self, Parameter::EnemyRule(_))
+    }
+    /// What to call one of this parameter's values in reports.
+    pub fn label(&self, value: u32) -> String {
+        match *self {
+            Parameter::EnemyRule(ref rules) => rules[value as usize].name(),
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// One dimension of a sweep.
+#[derive(Clone,Debug)]
+pub struct Axis {
+    /// What to call this axis in reports.
+    pub name: String,
+    pub parameter: Parameter,
+    pub values: Vec<u32>,
+}
+
+/// The result at one point of a sweep.
+#[derive(Clone,Debug)]
+pub struct SweepPoint {
+    /// The value of each axis at this point.
+    pub coordinates: Vec<u32>,
+    /// The simulation results, if this point was simulated.
+    pub tally: Option<Tally>,
+    /// The victory odds with each strategy, exact or simulated.
+    pub victory_odds: Vec<f64>,
+}
+
+/// Returns the coordinates of every point on the grid, with the last axis
+/// varying fastest.
+pub fn grid(axes: &[Axis]) -> Vec<Vec<u32>> {
+    let mut ret = vec![Vec::new()];
+    for axis in axes.iter() {
+        ret = ret.into_iter().flat_map(|point| {
+            axis.values.iter().map(move |&x| {
+                let mut point = point.clone();
+                point.push(x);
+                point
+            })
+        }).collect();
+    }
+    ret
+}
+
+/// Solves or simulates `base` at every point of the grid. If `units` is
+/// `None`, every point is solved exactly, spreading the points over
+/// `plan.tcount` threads. Otherwise, the points are simulated one after
+/// another, each with that many work units spread over the threads the same
+/// way as a single scenario; point N gets work units starting at N times
+/// `units`, so the results depend only on the seed. Fails if any point can't
+/// be prepared.
+pub fn run_sweep(base: &Scenario, axes: &[Axis],
+                 strategies: &[Box<dyn Strategy>], plan: WorkPlan, seed: u64,
+                 units: Option<usize>) -> Result<Vec<SweepPoint>, String> {
+    let points = grid(axes);
+    let prepare = |n: usize| {
+        let mut scenario = base.clone();
+        for (axis, &value) in axes.iter().zip(points[n].iter()) {
+            axis.parameter.apply(&mut scenario, value);
+        }
+        scenario.prepare()
+    };
+    match units {
+        None => parallel_map(plan.tcount, points.len(), |n| {
+            let boarding = prepare(n)?;
+            Ok(SweepPoint {
+                coordinates: points[n].clone(),
+                tally: None,
+                victory_odds: strategies.iter()
+                    .map(|x| boarding.exact_victory_odds(x.as_ref())).collect(),
+            })
+        }).into_iter().collect(),
+        Some(units) => (0 .. points.len()).map(|n| {
+            let boarding = prepare(n)?;
+            let tally = Tally::total(&run_work_units(&boarding, strategies, plan, seed,
+                                                     n * units, units));
+            Ok(SweepPoint {
+                coordinates: points[n].clone(),
+                victory_odds: tally.victories.iter()
+                    .map(|&x| x as f64 / tally.trials as f64).collect(),
+                tally: Some(tally),
+            })
+        }).collect(),
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Government;
+use crate::game_data::GameData;
+use crate::simulation::{Scenario, Side, parallel_map};
+use crate::strategy::*;
+use crate::optimize::TIE;
+
+/// How you would fare boarding one kind of ship, crewed by one kind of
+/// government.
+#[derive(Clone,Debug)]
+pub struct Target {
+    pub ship: String,
+    /// Every government whose crews are this strong, in alphabetical order.
+    pub governments: Vec<String>,
+    pub crew: u32,
+    /// The odds the game gives you of capturing it.
+    pub game_victory_odds: f64,
+    /// The exact victory odds with each strategy, in order.
+    pub victory_odds: Vec<f64>,
+    /// The strategy with the best odds. If several tie, the first of them.
+    pub best_strategy: usize,
+    /// The crew you can expect to lose with the best strategy, however the
+    /// fight ends.
+    pub casualties: f64,
+}
+
+impl Target {
+    pub fn best_victory_odds(&self) -> f64 {
+        self.victory_odds[self.best_strategy]
+    }
+}
+
+/// Groups the governments that have the same crew strengths, since they
+/// would all fight the same, and sorts each group's names.
+fn government_groups(data: &GameData) -> Vec<(Government, Vec<String>)> {
+    let mut ret: Vec<(Government, Vec<String>)> = Vec::new();
+    for (name, &government) in data.governments.iter() {
+        match ret.iter_mut().find(|x| x.0 == government) {
+            Some(group) => group.1.push(name.clone()),
+            None => ret.push((government, vec![name.clone()])),
+        }
+    }
+    for group in ret.iter_mut() { group.1.sort() }
+    ret.sort_by(|a, b| a.1.cmp(&b.1));
+    ret
+}
+
+/// Solves a boarding action against every ship in the data files that has
+/// any crew, once for each distinct government, in order of ship name.
+/// Fails if you have no crew.
+pub fn evaluate_targets(you: &Side, enemy_rule: EnemyRule, data: &GameData,
+                        strategies: &[Box<dyn Strategy>], tcount: usize)
+                        -> Result<Vec<Target>, String> {
+    let mut ships: Vec<_> = data.ships.values()
+        .map(|ship| (ship, ship.crew(data)))
+        .filter(|&(_, crew)| crew > 0)
+        .collect();
+    ships.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    let governments = government_groups(data);
+    let count = ships.len() * governments.len();
+    parallel_map(tcount, count, |n| {
+        let (ship, crew) = ships[n / governments.len()];
+        let (government, ref names) = governments[n % governments.len()];
+        let mut scenario = Scenario::new(you.clone(),
+                                         Side::new(crew, government, ship.weapons(data)));
+        scenario.enemy_rule = enemy_rule;
+        let boarding = scenario.prepare()?;
+        let solutions: Vec<_> = strategies.iter()
+            .map(|x| boarding.exact_solution(x.as_ref()))
+            .collect();
+        let victory_odds: Vec<f64> = solutions.iter()
+            .map(|x| x.victory_odds(boarding.ucrew, boarding.mcrew))
+            .collect();
+        let casualties: Vec<f64> = solutions.iter()
+            .map(|x| x.casualties(boarding.ucrew, boarding.mcrew))
+            .collect();
+        // odds within rounding error of each other are a tie, which goes to
+        // whichever loses fewer crew
+        let best_strategy = (0 .. victory_odds.len()).fold(0, |a, x| {
+            if victory_odds[x] > victory_odds[a] + TIE
+                || (victory_odds[x] >= victory_odds[a] - TIE && casualties[x] < casualties[a] - TIE) { x }
+            else { a }
+        });
+        Ok(Target {
+            ship: ship.name.clone(),
+            governments: names.clone(),
+            crew,
+            game_victory_odds: boarding.game_victory_odds(),
+            casualties: casualties[best_strategy],
+            victory_odds,
+            best_strategy,
+        })
+    }).into_iter().collect()
+}